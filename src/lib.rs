@@ -6,10 +6,167 @@ use std::collections::VecDeque;
 pub const UNICODE_SYMBOLS: [char; 13] = ['┼','┤','╶','╴','─','╰' ,'╭','╮','╯','│','╞','═','╤'];
 pub const   ASCII_SYMBOLS: [char; 13] = ['L','I','<','>','_','\\','.','.','/','|','v','-','v'];
 
+// lower 1/8 to 8/8 blocks, followed by upper half block for bars going downward
+pub const UNICODE_BARS: [char; 9] = ['▁','▂','▃','▄','▅','▆','▇','█','▀'];
+pub const   ASCII_BARS: [char; 9] = ['_','_','.','.','o','o','O','#','"'];
+
+// shades for heatmap from the lowest to the highest, or 256-color codes of the gradient
+pub const HEAT_SHADES: [char; 10] = [' ','.',':','-','=','+','*','#','%','@'];
+pub const HEAT_COLORS: [u8; 10] = [17, 19, 21, 33, 45, 49, 118, 226, 208, 196];
+
+// left 1/8 to 8/8 blocks for horizontal bars
+pub const UNICODE_HBARS: [char; 8] = ['▏','▎','▍','▌','▋','▊','▉','█'];
+pub const   ASCII_HBARS: [char; 8] = ['.','.',':',':','=','=','#','#'];
+
+// wick and body of candlesticks
+pub const UNICODE_CANDLES: [char; 2] = ['│','┃'];
+pub const   ASCII_CANDLES: [char; 2] = ['|','#'];
+
+// markers for each series in points mode, cycled if there are more series
+pub const UNICODE_POINTS: [char; 8] = ['●','×','◆','○','■','+','◇','□'];
+pub const   ASCII_POINTS: [char; 8] = ['*','x','o','+','#','@','%','&'];
+
+// # of rows in the plot at most, when neither height nor terminal size is given
+pub const DEFAULT_MAX_HEIGHT: usize = 40;
+
+// how the datapoints are drawn in the plot area
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mode {
+    #[default]
+    Line,
+    // braille dot patterns, with 2x4 dots in each cell
+    Braille,
+    // vertical bars from the baseline, grouped side by side for multiple series
+    Bars,
+    // a single line of blocks for each series, without axis and labels
+    Sparkline,
+    // a marker for each datapoint, without connecting lines
+    Points,
+    // a row of shades for each series
+    Heatmap,
+    // a horizontal bar of the latest value for each series
+    Hbar,
+    // candlesticks, from four series of open, high, low and close
+    Ohlc,
+    // lines holding each value for a column, then jumping to the next value
+    Step,
+}
+
+// how datapoints are connected in line mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum Style {
+    #[default]
+    Line,
+    Step,
+}
+
+// which lines of the background grid to draw
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum Grid {
+    #[default]
+    Both,
+    X,
+    Y,
+}
+
+// where to put the legend of series
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum Legend {
+    Top,
+    Bottom,
+    #[default]
+    Right,
+}
+
+// how values of y labels are written
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum YFormat {
+    #[default]
+    Plain,
+    // with metric prefixes, e.g. 1.2k, 3.4M
+    Si,
+    // with binary prefixes, e.g. 1.5KiB
+    Bytes,
+    // of seconds, in us, ms, s, min or h
+    Duration,
+    // of ratios, e.g. 0.25 as 25%
+    Percent,
+    // e.g. 1.5e-7
+    Scientific,
+    // with exponents in multiples of 3, e.g. 150.0e-9
+    Engineering,
+    // prefix, precision and suffix
+    Custom(String, Option<usize>, String),
+}
+
+// how datapoints are grouped into a candle
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Bucket {
+    Samples(u64),
+    Seconds(f64),
+}
+
+impl Mode {
+    // column of the plot where `i`th datapoint is drawn, counting from the one right of the axis.
+    // None if it's on the axis.
+    pub fn column(&self, i: usize, series: usize) -> Option<usize> {
+        match self {
+            Mode::Braille => Some(i / 2),
+            Mode::Bars => Some(i * (series.max(1) + (series > 1) as usize)),
+            Mode::Step => (2 * i).checked_sub(1), // where it jumps to the value
+            Mode::Line | Mode::Points => i.checked_sub(1),
+            _ => Some(i),
+        }
+    }
+
+    // column of the first datapoint counted from the vertical axis, where x labels start.
+    pub fn first_column(&self, series: usize) -> usize {
+        self.column(0, series).map_or(0, |x| x + 1)
+    }
+
+    // # of datapoints that fit in a plot of `width` columns, drawing `series` series.
+    // the first column is the vertical axis, which is not drawn on except for the first datapoint.
+    pub fn datapoints(&self, width: usize, series: usize) -> usize {
+        match self {
+            Mode::Braille => 2 * width.saturating_sub(1),
+            Mode::Bars => {
+                let gap = (series > 1) as usize; // a blank column between groups
+                (width.saturating_sub(1) + gap) / (series.max(1) + gap)
+            },
+            Mode::Heatmap | Mode::Ohlc => width.saturating_sub(1),
+            Mode::Step => width / 2, // a column for holding, and another for jumping
+            _ => width,
+        }
+    }
+
+    // # of columns needed to draw `len` datapoints, inverse of `datapoints`.
+    pub fn columns(&self, len: usize, series: usize) -> usize {
+        if len == 0 { return 0; }
+        match self {
+            Mode::Braille => 1 + len.div_ceil(2),
+            Mode::Bars => {
+                let gap = (series > 1) as usize;
+                1 + len * (series.max(1) + gap) - gap
+            },
+            Mode::Heatmap | Mode::Ohlc => 1 + len,
+            Mode::Step => 2 * len,
+            _ => len,
+        }
+    }
+}
+
+// dot bits of braille pattern (U+2800), indexed by [dx][dy] where dy counts from the bottom
+const BRAILLE_DOTS: [[u32; 4]; 2] = [[0x40, 0x04, 0x02, 0x01], [0x80, 0x20, 0x10, 0x08]];
+
 #[derive(Debug)]
 pub struct Config {
     pub symbols: [char; 13],
-    pub width: usize, // TODO TEST None for variate
+    pub bar_symbols: [char; 9],
+    pub hbar_symbols: [char; 8],
+    pub candle_symbols: [char; 2],
+    pub point_symbols: [char; 8],
+    pub mode: Mode,
+    pub width: usize, // # of columns, not datapoints. TODO TEST None for variate
     // what if w=0 or h=0?
 
     pub label_bot: f64,
@@ -17,137 +174,790 @@ pub struct Config {
     pub v_step: f64,
 
     pub label_precision: usize,
+    pub y_format: YFormat,
+    // values are log10(1+x) of counts, labeled with x
+    pub log_scale: bool,
+    // label_top at the bottom row and label_bot at the top
+    pub invert: bool,
+    // marks of values clipped above and below the plot
+    pub overflow_symbols: [char; 2],
 
     // x_start, x_step, x_prec, x_interval
     pub x_label: Option<(f64,f64,usize,usize)>,
+
+    // name of each series, empty if not named
+    pub names: Vec<String>,
+    // show last, min and max values of each series in sparkline
+    pub summary: bool,
+
+    // glyph to shade the area between the line and the baseline
+    pub fill: Option<char>,
+    // glyph to shade the band between the first two series, which are not drawn as lines
+    pub band: Option<char>,
+
+    // value, color and label of horizontal lines drawn under the series
+    pub hlines: Vec<(f64,u32,String)>,
+    pub dash_symbol: char,
+
+    // datapoint index and label of events, marked with vertical lines
+    pub events: Vec<(usize,String)>,
+    pub event_symbol: char,
+
+    // dotted lines at each row (y) and at each x tick (x), under everything else
+    pub grid: (bool,bool),
+    pub grid_symbol: char,
+
+    // position of the legend, and whether to show the last value of each series
+    pub legend: Option<(Legend,bool)>,
+    pub swatch_symbol: char,
+
+    // text centered above the plot, under the x labels, and stacked left of the y labels
+    pub title: Option<String>,
+    pub x_caption: Option<String>,
+    pub y_caption: Option<String>,
+
+    // indices of series to draw on top of the others, the topmost first
+    pub priority: Vec<usize>,
+    // glyph where lines of two series cross, in the blend of their colors
+    pub cross_symbol: char,
+
+    // bins, lo, hi, log-scaled. draws counts of values in each bin instead of the values
+    pub histogram: Option<(usize,f64,f64,bool)>,
+
+    // use background colors instead of shade characters for heatmap
+    pub gradient: bool,
+}
+
+impl Config {
+    // # of rows for the plot area, a row for each y label
+    pub fn rows(&self) -> usize {
+        let v_step = if self.v_step == 0. {f64::MIN_POSITIVE} else {self.v_step};
+        let intv = self.label_top - self.label_bot;
+        if intv == 0. {1} else {1 + (self.label_top/v_step - self.label_bot/v_step).round() as usize}
+    }
+
+    // writes the value as a y label, with `label_precision` digits after the point
+    pub fn format_value(&self, v: f64) -> String {
+        let prec = self.label_precision;
+        let v = if self.log_scale {10f64.powf(v) - 1.} else {v};
+        if !v.is_finite() { return format!("{:.1$}", v, prec); }
+
+        // scales the value down by the largest unit not exceeding it
+        let scaled = |base: f64, units: &[&str], lowest: i32| {
+            let exp = if v == 0. {0} else {(v.abs().log(base).floor() as i32).clamp(lowest, lowest + units.len() as i32 - 1)};
+            format!("{:.2$}{}", v / base.powi(exp), units[(exp - lowest) as usize], prec)
+        };
+
+        match &self.y_format {
+            YFormat::Plain => format!("{:.1$}", v, prec),
+            YFormat::Si => scaled(1000., &["p","n","µ","m","","k","M","G","T","P","E"], -4),
+            YFormat::Bytes => scaled(1024., &["B","KiB","MiB","GiB","TiB","PiB","EiB"], 0),
+            YFormat::Duration => {
+                let (unit, div) = [("h", 3600.), ("min", 60.), ("s", 1.), ("ms", 1e-3)].into_iter()
+                    .find(|&(_, div)| v.abs() >= div).unwrap_or(("us", 1e-6));
+                format!("{:.2$}{}", v / div, unit, prec)
+            },
+            YFormat::Percent => format!("{:.1$}%", v * 100., prec),
+            YFormat::Scientific => format!("{:.1$e}", v, prec),
+            YFormat::Engineering => {
+                let exp = if v == 0. {0} else {(v.abs().log10() / 3.).floor() as i32 * 3};
+                format!("{:.2$}e{}", v / 10f64.powi(exp), exp, prec)
+            },
+            YFormat::Custom(prefix, p, suffix) => format!("{}{:.3$}{}", prefix, v, suffix, p.unwrap_or(prec)),
+        }
+    }
 }
 
 // TODO flowing x label when monitoring?
 
-pub fn plot(vss: &Vec<(VecDeque<f64>,u32)>, cfg: Config) -> (String, usize) {
+pub fn plot(vss: &[(VecDeque<f64>,u32)], cfg: Config) -> (String, usize) {
+    if cfg.mode == Mode::Sparkline {
+        return sparkline(vss, cfg);
+    }
+
+    if cfg.mode == Mode::Heatmap {
+        return heatmap(vss, cfg);
+    }
+
+    if cfg.mode == Mode::Hbar {
+        return hbar(vss, cfg);
+    }
+
+    if let Some((bins, lo, hi, log)) = cfg.histogram {
+        let counts = histogram(vss, bins, lo, hi, log);
+        return plot(&counts, Config { histogram: None, ..cfg });
+    }
+
+    // at least the axis column, even without datapoints
+    let cfg = Config { width: cfg.width.max(1), ..cfg };
+
     assert!(cfg.label_bot <= cfg.label_top);
-    assert!(cfg.v_step >= 0.); // TODO v_step < 0 && label_bot > label_top for inverted??
+    assert!(cfg.v_step >= 0.); // inverted plots keep the order of labels, flipping rows instead
     assert!(cfg.x_label.filter(|x_label| x_label.3 == 0).is_none());
 
     let v_step = if cfg.v_step == 0. {f64::MIN_POSITIVE} else {cfg.v_step};
     // keep the value positive
 
-    let height = {
-        let intv = cfg.label_top - cfg.label_bot;
-        if intv == 0. {1} else {1 + (intv/v_step).round() as usize}
-    };
+    let height = cfg.rows();
+
+    let mut labels = (0..height).map(|y| cfg.format_value(if y == height-1 {
+        cfg.label_top // to avoid top label being like 1.9999999 for float error
+    } else {
+        cfg.label_bot + (y as f64) * v_step
+    })).collect::<Vec<_>>();
+    if cfg.invert { labels.reverse(); }
 
     let label_margin = {
-        let label_bodywidth = {
-            // FIXME WHAT IF label_top == label_bot == 0. ??
-            let bot_width = 1 + if cfg.label_bot < 0. {1} else {0} + cfg.label_bot.abs().log10().floor() as usize;
-            let top_width = 1 + if cfg.label_top < 0. {1} else {0} + cfg.label_top.abs().log10().floor() as usize;
-            bot_width.max(top_width)
-        };
-        let abs_width = label_bodywidth + // add 1 for midpoint if precision is not 0
-            if cfg.label_precision == 0 {0} else { 1 + cfg.label_precision };
+        let abs_width = labels.iter().map(|l| display_width(l)).max().unwrap_or(0);
         // left space 1, the number with ljust, right space 1
-        1 + abs_width + 1
+        // and the caption with a space, if any
+        1 + abs_width + 1 + if cfg.y_caption.is_some() {2} else {0}
     };
 
+    // labels of horizontal lines go right of the plot, after a space
+    let hline_margin = cfg.hlines.iter().map(|(_,_,label)| label.chars().count())
+        .filter(|&w| w > 0).max().map_or(0, |w| 1 + w);
+
     // note that each row had length `label_margin + cfg.width`, omitting 1 for mid-axis character.
     // this is because, the axis point is used to represent first data point.
-    let mut buffer = vec![vec![(' ', 9); label_margin + cfg.width]; height];
-
-    for y in 0..height {
-        let label = format!(
-            "{number:LW$.PREC$} ",
-            LW = label_margin - 1, // subtract 1 for the trailing space
-            PREC = cfg.label_precision,
-            number = if y == height-1 {
-                cfg.label_top // to avoid top label being like 1.9999999 for float error
-            } else {
-                cfg.label_bot + (y as f64) * v_step
-            },
-        );
-        for (i,c) in label.chars().enumerate() { buffer[y][i] = (c, 9); }
-        buffer[y][label_margin] = (cfg.symbols[1], 9); // '┤' axis char
+    let mut buffer = vec![vec![(' ', 9); label_margin + cfg.width + hline_margin]; height];
+    // the layer under the series, shown only where nothing is drawn on the buffer
+    let mut background = buffer.clone();
+
+    for (row, label) in buffer.iter_mut().zip(&labels) {
+        // subtract 1 for the trailing space
+        let label = format!("{}{} ", " ".repeat(label_margin - 1 - display_width(label)), label);
+        for (i,c) in label.chars().enumerate() { row[i] = (c, 9); }
+        row[label_margin] = (cfg.symbols[1], 9); // '┤' axis char
+    }
+
+    // a character per row, centered vertically
+    if let Some(caption) = &cfg.y_caption {
+        let len = caption.chars().count().min(height);
+        for (i, c) in caption.chars().take(len).enumerate() {
+            buffer[height - 1 - (height - len)/2 - i][1] = (c, 9); // after the left space
+        }
     }
 
     // scale the value into row index. `-1` if too low, `height` if too high, `None` if NaN
-    let scaled = |v :f64| (!v.is_nan()).then_some(
+    let upright = |v :f64| (!v.is_nan()).then_some(
         if v < cfg.label_bot - v_step/2. { -1i32 }
         else if cfg.label_top + v_step/2. < v { height as i32 }
-        else if v_step != 0. { ((v-cfg.label_bot)/v_step).round() as i32 }
+        else if v_step != 0. { (v/v_step - cfg.label_bot/v_step).round() as i32 }
         else {0}
     );
+    // upside down if inverted, so that corners and clipping follow the flipped rows
+    let scaled = |v :f64| upright(v).map(|y| if cfg.invert {height as i32 - 1 - y} else {y});
 
     // margin + axis char 1
     let offset = label_margin + 1;
 
-    for (vs,color) in vss {
+    // braille dots of each cell, accumulated over series then converted to chars
+    let mut dots = vec![vec![0u32; cfg.width]; height];
+
+    // series whose line is drawn on each cell, to find where lines cross
+    let mut drawn = vec![vec![None; cfg.width]; height];
+
+    // scale the value into braille dot row, 4 rows per cell. clipped values are kept
+    // just outside the plot (`-1` or `4*height`) so that lines to them are still drawn.
+    let dot_row = |v :f64| upright(v).map(|y| {
+        let s = ((v/v_step - cfg.label_bot/v_step + 0.5) * 4.).floor() as i32;
+        let s = if y < 0 { -1 } else if y >= height as i32 { 4 * height as i32 } else { s.clamp(4*y, 4*y+3) };
+        if cfg.invert { 4 * height as i32 - 1 - s } else { s }
+    });
+
+    let datapoints = cfg.mode.datapoints(cfg.width, vss.len());
+
+    // grid goes first, under everything else
+    if cfg.grid.0 {
+        for row in background.iter_mut() {
+            for cell in &mut row[offset .. label_margin + cfg.width] {
+                *cell = (cfg.grid_symbol, 9); // '·'
+            }
+        }
+    }
+    if let (true, Some((.., x_intv))) = (cfg.grid.1, cfg.x_label) {
+        // at the ticks of x-axis, which are the columns of x labels
+        let first = label_margin + cfg.mode.first_column(vss.len());
+        for x in (first + x_intv .. label_margin + cfg.width).step_by(x_intv) {
+            for row in background.iter_mut() {
+                row[x] = (cfg.grid_symbol, 9); // '·'
+            }
+        }
+    }
+
+    // then horizontal lines, under the series
+    for (v, color, label) in &cfg.hlines {
+        let Some(y) = scaled(*v).filter(|&y| 0 <= y && y < height as i32) else { continue };
+        let row = &mut background[y as usize];
+        for cell in &mut row[offset .. label_margin + cfg.width] {
+            *cell = (cfg.dash_symbol, *color); // '╌'
+        }
+        for (i, c) in label.chars().enumerate() {
+            row[label_margin + cfg.width + 1 + i] = (c, *color);
+        }
+    }
+
+    // event markers, also under the series. an event on the first datapoint replaces the axis
+    let events = cfg.events.iter().filter(|(i, _)| *i < datapoints).filter_map(|(i, label)| {
+        match cfg.mode.column(*i, vss.len()) {
+            Some(x) if x+1 < cfg.width => Some((x + offset, label)),
+            Some(_) => None,
+            None => Some((offset - 1, label)), // on the axis
+        }
+    }).collect::<Vec<_>>();
+    for &(x, _) in &events {
+        let layer = if x < offset { &mut buffer } else { &mut background };
+        for row in layer.iter_mut() {
+            row[x] = (cfg.event_symbol, 9); // '┊'
+        }
+    }
+
+    // shade areas of all series first, so that no line gets covered by another's area
+    if let (Some(fill), Mode::Line | Mode::Points | Mode::Step) = (cfg.fill, cfg.mode) {
+        // row the area starts from. zero if it's in range, the bottom otherwise
+        let base = scaled(0.).filter(|&y| 0 <= y && y < height as i32).unwrap_or(0);
+        for (vs,color) in vss {
+            for x in 0 .. cfg.width.saturating_sub(1) {
+                // datapoint drawn at the column, the one jumped to in step mode
+                let i = if cfg.mode == Mode::Step {x.div_ceil(2)} else {x+1};
+                let Some(y) = vs.get(i).filter(|_| i < datapoints).and_then(|&v| scaled(v)) else { continue };
+                let y = y.clamp(0, height as i32 - 1);
+                for y in y.min(base) ..= y.max(base) {
+                    buffer[y as usize][x+offset] = (fill, *color);
+                }
+            }
+        }
+    }
+
+    if let (Mode::Ohlc, [open, high, low, close]) = (cfg.mode, vss) {
+        let ohlc = open.0.iter().zip(&high.0).zip(&low.0).zip(&close.0).take(datapoints);
+        for (x, (((&o, &h), &l), &c)) in ohlc.enumerate() {
+            let color = if c >= o {2} else {1}; // green if went up, red otherwise
+            let mut put = |v0: f64, v1: f64, chr| {
+                if let (Some(y0), Some(y1)) = (scaled(v0), scaled(v1)) {
+                    for y in y0.min(y1).max(0) ..= y0.max(y1).min(height as i32 - 1) {
+                        buffer[y as usize][x+offset] = (chr, color);
+                    }
+                }
+            };
+            put(l, h, cfg.candle_symbols[0]); // '│' wick
+            put(o, c, cfg.candle_symbols[1]); // '┃' body
+        }
+    }
 
-        let vvs = vs.iter().cloned().take(cfg.width).tuple_windows();
-        for (x,(v0,v1)) in vvs.enumerate() { // runs at most width-1 times
+    // spans between lower and upper bounds, under the lines
+    if let (Some(band), [(lo, color), (hi, _), ..]) = (cfg.band, vss) {
+        for x in 0 .. cfg.width.saturating_sub(1) {
+            let i = if cfg.mode == Mode::Step {x.div_ceil(2)} else {x+1};
+            let bounds = lo.get(i).zip(hi.get(i)).filter(|_| i < datapoints);
+            let Some((Some(y0), Some(y1))) = bounds.map(|(&v0, &v1)| (scaled(v0), scaled(v1))) else { continue };
+            for y in y0.min(y1).max(0) ..= y0.max(y1).min(height as i32 - 1) {
+                buffer[y as usize][x+offset] = (band, *color);
+            }
+        }
+    }
 
-            let mut put = |y, x, chr| if let Ok(y) = usize::try_from(y) {
+    // series with priority are drawn last, the topmost at the end
+    let order = (0..vss.len()).filter(|k| !cfg.priority.contains(k))
+        .chain(cfg.priority.iter().rev().cloned().filter(|&k| k < vss.len()));
+    for (k, (vs,color)) in order.map(|k| (k, &vss[k])) {
+        // candles are drawn above, from all four series together
+        if cfg.mode == Mode::Ohlc { break; }
+        // and so are the bounds of band
+        if cfg.band.is_some() && k < 2 { continue; }
+
+        if cfg.mode == Mode::Braille {
+            let ss = vs.iter().cloned().take(datapoints).map(dot_row).collect::<Vec<_>>();
+            for (i, s) in ss.iter().enumerate() {
+                let Some(s) = *s else { continue };
+                // connect from the previous dot, which is already drawn
+                let range = match i.checked_sub(1).and_then(|j| ss[j]) {
+                    Some(p) if p < s => p+1 ..= s,
+                    Some(p) if p > s => s ..= p-1,
+                    _ => s ..= s,
+                };
+                let x = i / 2;
+                for s in range.filter(|&s| 0 <= s && s < 4 * height as i32) {
+                    let (y, dy) = (s as usize / 4, s as usize % 4);
+                    dots[y][x] |= BRAILLE_DOTS[i % 2][dy];
+                    buffer[y][x+offset].1 = *color;
+                }
+            }
+        } else if cfg.mode == Mode::Bars {
+            let gap = (vss.len() > 1) as usize;
+            // position in row units, where the value of row `r` is at `r + 0.5`.
+            // a single row (zero v_step) holds its value in the middle, and nothing else
+            let pos = |v: f64| if cfg.v_step != 0. {v/v_step - cfg.label_bot/v_step + 0.5}
+                else if v < cfg.label_bot {0.} else if v > cfg.label_bot {1.} else {0.5};
+            // bars start from zero, or from the edge if zero is out of range
+            let base = pos(0.).clamp(0., height as f64);
+            for (i, v) in vs.iter().cloned().take(datapoints).enumerate() {
+                if v.is_nan() { continue; }
+                let x = i * (vss.len() + gap) + k + offset;
+                let u = pos(v).clamp(0., height as f64);
+                let (lo, hi) = (u.min(base), u.max(base));
+                let rows = buffer.iter_mut().enumerate().take(hi.ceil() as usize).skip(lo.floor() as usize);
+                for (r, row) in rows {
+                    // part of the row covered by the bar
+                    let (a, b) = (lo.max(r as f64), hi.min(r as f64 + 1.));
+                    let eighths = ((b - a) * 8.).round() as usize;
+                    let chr = if a == r as f64 || b < r as f64 + 1. {
+                        if eighths == 0 { continue; }
+                        cfg.bar_symbols[eighths.min(8) - 1] // '▁' to '█'
+                    } else if eighths >= 2 { // there's no upper blocks other than half, so approximate
+                        if eighths >= 6 {cfg.bar_symbols[7]} else {cfg.bar_symbols[8]} // '█', '▀'
+                    } else { continue };
+                    row[x] = (chr, *color);
+                }
+            }
+        } else if cfg.mode == Mode::Points {
+            // the first datapoint is on the axis, marked below
+            for (x, v) in vs.iter().cloned().take(datapoints).skip(1).enumerate() {
+                if let Some(y) = scaled(v).filter(|&y| 0 <= y && y < height as i32) {
+                    buffer[y as usize][x+offset] = (cfg.point_symbols[k % 8], *color);
+                }
+            }
+        } else {
+            let step = cfg.mode == Mode::Step;
+            let mut put = |y, x: usize, chr| if let Ok(y) = usize::try_from(y) {
                 if y < height {
-                    buffer[y as usize][x+offset] = (chr, *color);
+                    let cell = &mut buffer[y][x+offset];
+                    *cell = match drawn[y][x].replace(k) {
+                        // crossing another series, or running along it with the same glyph
+                        Some(j) if j != k && cell.0 != chr => {
+                            let blend = if cell.1 < 8 && *color < 8 {cell.1 | *color} else {*color};
+                            (cfg.cross_symbol, blend) // '┼'
+                        },
+                        _ => (chr, *color),
+                    };
                 }
             };
 
-            match (scaled(v0), scaled(v1)) {
-                (None, None) => continue,
-                (None, Some(y)) =>
-                    put(y, x, cfg.symbols[2]), // '╶'
-                (Some(y), None) =>
-                    put(y, x, cfg.symbols[3]), // '╴'
-                (Some(y0), Some(y1)) if y0 == y1 =>
-                    put(y0, x, cfg.symbols[4]), // '─'
-                (Some(y0), Some(y1)) => {
-                    put(y1, x, if y0 > y1 {cfg.symbols[5]} else {cfg.symbols[6]}); // '╰', '╭'
-                    put(y0, x, if y0 > y1 {cfg.symbols[7]} else {cfg.symbols[8]}); // '╮', '╯'
-
-                    for y in y0.min(y1)+1 ..= y0.max(y1)-1 {
-                        put(y, x, cfg.symbols[9]); // '│'
-                    }
-                },
+            // in step mode, each value is held for a column before jumping to the next
+            if let (true, Some(y)) = (step, vs.front().and_then(|&v| scaled(v))) {
+                put(y, 0, cfg.symbols[4]); // '─'
+            }
+
+            let vvs = vs.iter().cloned().take(datapoints).tuple_windows();
+            for (i,(v0,v1)) in vvs.enumerate() { // runs at most width-1 times
+                let x = if step {2*i+1} else {i};
+                if let (true, Some(y)) = (step, scaled(v1)) {
+                    put(y, x+1, cfg.symbols[4]); // '─'
+                }
+
+                match (scaled(v0), scaled(v1)) {
+                    (None, None) => continue,
+                    (None, Some(y)) =>
+                        put(y, x, cfg.symbols[2]), // '╶'
+                    (Some(y), None) =>
+                        put(y, x, cfg.symbols[3]), // '╴'
+                    (Some(y0), Some(y1)) if y0 == y1 =>
+                        put(y0, x, cfg.symbols[4]), // '─'
+                    (Some(y0), Some(y1)) => {
+                        put(y1, x, if y0 > y1 {cfg.symbols[5]} else {cfg.symbols[6]}); // '╰', '╭'
+                        put(y0, x, if y0 > y1 {cfg.symbols[7]} else {cfg.symbols[8]}); // '╮', '╯'
+
+                        for y in y0.min(y1)+1 ..= y0.max(y1)-1 {
+                            put(y, x, cfg.symbols[9]); // '│'
+                        }
+                    },
+                }
             }
         }
 
         // for first valut, mark it on the vertical axis (continued axis)
-        if let Some(&v) = vs.get(0) { if let Some(y) = scaled(v) {
+        if cfg.mode == Mode::Bars { continue; }
+        if let Some(&v) = vs.front() { if let Some(y) = scaled(v) {
             if 0 <= y && y < height as i32 {
-                buffer[y as usize][offset-1] = (cfg.symbols[0], *color); // '┼' continued axis char
+                // each series keeps its own marker in points mode
+                let chr = if cfg.mode == Mode::Points {cfg.point_symbols[k % 8]} else {cfg.symbols[0]}; // '┼' continued axis char
+                buffer[y as usize][offset-1] = (chr, *color);
             }
         }}
 
     }
 
+    for (y, row) in dots.iter().enumerate() {
+        for (x, &bits) in row.iter().enumerate().filter(|(_, &bits)| bits != 0) {
+            buffer[y][x+offset].0 = char::from_u32(0x2800 + bits).unwrap();
+        }
+    }
+
+    // clipped values are marked on the edge rows they go beyond, except for infinities
+    for (k, (vs,color)) in vss.iter().enumerate() {
+        if cfg.mode == Mode::Ohlc { break; }
+        if cfg.band.is_some() && k < 2 { continue; }
+        for (i, &v) in vs.iter().take(datapoints).enumerate() {
+            let (y, chr) = match scaled(v).filter(|_| v.is_finite()) {
+                Some(y) if y < 0 => (0, cfg.overflow_symbols[1]), // '▼'
+                Some(y) if y >= height as i32 => (height-1, cfg.overflow_symbols[0]), // '▲'
+                _ => continue,
+            };
+            let x = match cfg.mode.column(i, vss.len()) {
+                Some(x) if cfg.mode == Mode::Bars => x + k + offset,
+                Some(x) => x + offset,
+                None => offset - 1, // on the axis
+            };
+            if x < label_margin + cfg.width {
+                buffer[y][x] = (chr, *color);
+            }
+        }
+    }
+
+    // name of each series with its color, and the last value if asked
+    let legend = cfg.legend.filter(|_| cfg.mode != Mode::Ohlc).map(|(pos, values)| {
+        let names = row_names(&cfg, vss.len());
+        let entries = vss.iter().zip(names).enumerate().skip(if cfg.band.is_some() {2} else {0});
+        let entries = entries.map(|(k, ((vs,color), name))| {
+            let swatch = if cfg.mode == Mode::Points {cfg.point_symbols[k % 8]} else {cfg.swatch_symbol}; // '■'
+            let swatch = if *color == 9 {swatch.to_string()} else {format!("\x1b[3{}m{}\x1b[0m", color, swatch)};
+            let last = vs.iter().cloned().take(datapoints).rfind(|v| !v.is_nan());
+            match last.filter(|_| values) {
+                Some(v) => format!("{} {} {}", swatch, name, cfg.format_value(v)),
+                None => format!("{} {}", swatch, name),
+            }
+        }).collect::<Vec<_>>();
+        // a row for each entry doesn't fit on the right, so all of them go to the bottom
+        let pos = if pos == Legend::Right && entries.len() > height {Legend::Bottom} else {pos};
+        (pos, entries)
+    });
+
     let mut ret = String::new();
-    for line in buffer.into_iter().rev() {
-        for (chr, color) in line {
+    let offset_pad = " ".repeat(offset);
+
+    // centered over the plot area including the labels
+    let centered = |text: &str| {
+        let pad = (label_margin + cfg.width).saturating_sub(display_width(text)) / 2;
+        format!("{}{}", " ".repeat(pad), text)
+    };
+    if let Some(title) = &cfg.title {
+        writeln!(ret, "{}", centered(title)).unwrap();
+    }
+
+    if let Some((Legend::Top, entries)) = &legend {
+        writeln!(ret, "{}{}", offset_pad, entries.join("  ")).unwrap();
+    }
+
+    // labels of events above the plot, cut short not to overlap with the next one
+    if !events.is_empty() {
+        let mut line = String::new();
+        let mut events = events.into_iter().sorted_by_key(|(x, _)| *x).peekable();
+        while let Some((col, label)) = events.next() {
+            let pad = col.saturating_sub(display_width(&line));
+            let room = events.peek().map_or(usize::MAX, |(next, _)| next - col - 1).max(1);
+            line.extend(std::iter::repeat_n(' ', pad));
+            line.extend(label.chars().take(room));
+        }
+        writeln!(ret, "{}", line).unwrap();
+    }
+    // legend right of the plot takes a row for each series from the top
+    let mut right = match &legend {
+        Some((Legend::Right, entries)) => entries.iter(),
+        _ => [].iter(),
+    };
+    for (line, back) in buffer.into_iter().zip(background).rev() {
+        for (chr, color) in line.into_iter().zip(back).map(|(front, back)| if front.0 == ' ' {back} else {front}) {
             if color == 9 || chr == ' ' {
                 write!(ret, "{}", chr).unwrap();
             } else {
                 write!(ret, "\x1b[3{}m{}\x1b[0m", color, chr).unwrap();
             }
         }
-        write!(ret, "\n").unwrap();
+        if let Some(entry) = right.next() {
+            write!(ret, "  {}", entry).unwrap();
+        }
+        writeln!(ret).unwrap();
+    }
+
+    write_x_axis(&mut ret, &cfg, label_margin, vss.len());
+
+    if let Some(caption) = &cfg.x_caption {
+        writeln!(ret, "{}", centered(caption)).unwrap();
+    }
+
+    if let Some((Legend::Bottom, entries)) = &legend {
+        writeln!(ret, "{}{}", offset_pad, entries.join("  ")).unwrap();
     }
 
+    let height = ret.lines().count();
+    (ret, height)
+}
+
+// names to label each row of series, the column number if not named
+fn row_names(cfg: &Config, series: usize) -> Vec<String> {
+    (0..series).map(|k| cfg.names.get(k).cloned().unwrap_or((k+1).to_string())).collect()
+}
+
+// a horizontal bar for the latest value of each series, from zero (or label_bot if below) to label_top.
+// each line is the name, the bar, then the value, fitting in `cfg.width` columns.
+fn hbar(vss: &[(VecDeque<f64>,u32)], cfg: Config) -> (String, usize) {
+    let names = row_names(&cfg, vss.len());
+    let values = vss.iter().map(|(vs,_)| {
+        let v = vs.iter().cloned().rfind(|v| !v.is_nan()).unwrap_or(f64::NAN);
+        (v, cfg.format_value(v))
+    }).collect::<Vec<_>>();
+
+    let name_width = names.iter().map(|n| display_width(n)).max().unwrap_or(0);
+    let value_width = values.iter().map(|(_, s)| s.len()).max().unwrap_or(0);
+    // left space 1, name, space 1, axis char 1, bar, space 1, value
+    let bar_width = cfg.width.saturating_sub(name_width + value_width + 4);
+
+    let lo = cfg.label_bot.min(0.);
+    let intv = cfg.label_top - lo;
+
+    let mut ret = String::new();
+    for (((_,color), name), (v, value)) in vss.iter().zip(names).zip(values) {
+        let pad = " ".repeat(name_width - display_width(&name));
+        write!(ret, " {}{} {}", pad, name, cfg.symbols[1]).unwrap(); // '┤' axis char
+
+        let eighths = if v.is_nan() {0} else {
+            let ratio = if intv == 0. {1.} else {(v - lo) / intv};
+            (ratio.clamp(0., 1.) * (8 * bar_width) as f64).round() as usize
+        };
+        let mut bar = cfg.hbar_symbols[7].to_string().repeat(eighths / 8); // '█'
+        if eighths % 8 != 0 {
+            bar.push(cfg.hbar_symbols[eighths % 8 - 1]); // '▏' to '▉'
+        }
+        let bar_len = eighths.div_ceil(8);
+        if *color == 9 || bar.is_empty() {
+            write!(ret, "{}", bar).unwrap();
+        } else {
+            write!(ret, "\x1b[3{}m{}\x1b[0m", color, bar).unwrap();
+        }
+        writeln!(ret, "{} {:>2$}", " ".repeat(bar_width - bar_len), value, value_width).unwrap();
+    }
+
+    (ret, vss.len())
+}
+
+// writes x-axis and x-labels under the plot, if x_label is set.
+// `margin` is the width of y-labels, where x-axis starts right after.
+fn write_x_axis(ret: &mut String, cfg: &Config, margin: usize, series: usize) {
     if let Some((x_start,x_step,x_prec,x_intv)) = cfg.x_label {
+        // ticks are from the column of the first datapoint
+        let first = cfg.mode.first_column(series);
+
         // x-axis
-        write!(ret, "{: ^1$}", "", offset-1).unwrap();
-        let mut form = std::iter::repeat(cfg.symbols[11]).take(x_intv-1).collect::<String>();
-        form.push(cfg.symbols[12]);
-        let s = std::iter::repeat_with(||form.chars()).flatten().take(cfg.width).collect::<String>();
-        write!(ret, "{}{}\n", cfg.symbols[10], s).unwrap();
+        write!(ret, "{: ^1$}", "", margin).unwrap();
+        let s = (1..=cfg.width).map(|i| {
+            if i >= first && (i - first).is_multiple_of(x_intv) {cfg.symbols[12]} else {cfg.symbols[11]}
+        }).collect::<String>();
+        writeln!(ret, "{}{}", cfg.symbols[10], s).unwrap();
+
+        // x-labels at each tick, without trailing spaces beyond the x-axis
+        let mut line = String::new();
+        for i in (first..=cfg.width).step_by(x_intv) {
+            let x = x_start + x_step * cfg.mode.datapoints(i, series) as f64;
+            let label = format!("{:.1$}", x, x_prec);
+            let pad = i.saturating_sub(line.len());
+            line.extend(std::iter::repeat_n(' ', pad));
+            line.push_str(&label);
+        }
+        writeln!(ret, "{: ^1$}{2}", "", margin, line).unwrap();
+    }
+}
 
-        // x-labels
-        write!(ret, "{: ^1$}", "", offset-1).unwrap();
-        for i in (0..=cfg.width).step_by(x_intv) {
-            write!(ret, "{:<1$.2$}", x_start + x_step * i as f64, x_intv, x_prec).unwrap();
+// a row of shades for each series, darker for larger values in label_bot ~ label_top.
+// rows are labeled with series names, sharing x-axis with the other modes.
+fn heatmap(vss: &[(VecDeque<f64>,u32)], cfg: Config) -> (String, usize) {
+    let names = row_names(&cfg, vss.len());
+    // left space 1, the name with rjust, right space 1
+    let label_margin = 1 + names.iter().map(|n| display_width(n)).max().unwrap_or(0) + 1;
+
+    let intv = cfg.label_top - cfg.label_bot;
+    let datapoints = cfg.mode.datapoints(cfg.width, vss.len());
+
+    let mut ret = String::new();
+    for ((vs,_), name) in vss.iter().zip(names) {
+        let pad = " ".repeat(label_margin - 1 - display_width(&name));
+        write!(ret, "{}{} {}", pad, name, cfg.symbols[1]).unwrap(); // '┤' axis char
+
+        for &v in vs.iter().take(datapoints) {
+            if v.is_nan() {
+                write!(ret, " ").unwrap();
+                continue;
+            }
+            let level = if intv == 0. {0.5} else {(v - cfg.label_bot) / intv};
+            let level = (level * 9.).round().clamp(0., 9.) as usize;
+            if cfg.gradient {
+                write!(ret, "\x1b[48;5;{}m \x1b[0m", HEAT_COLORS[level]).unwrap();
+            } else {
+                write!(ret, "{}", HEAT_SHADES[level]).unwrap();
+            }
+        }
+        writeln!(ret).unwrap();
+    }
+
+    write_x_axis(&mut ret, &cfg, label_margin, vss.len());
+
+    (ret, vss.len() + if cfg.x_label.is_none() {0} else {2})
+}
+
+// aggregates datapoints of open, high, low and close series into candles,
+// grouping consecutive datapoints of the same key.
+pub fn candles(ohlc: &[VecDeque<f64>; 4], keys: &VecDeque<u64>) -> [VecDeque<f64>; 4] {
+    let mut ret: [VecDeque<f64>; 4] = Default::default();
+    let mut last = None;
+    for (i, &key) in keys.iter().enumerate() {
+        let [o, h, l, c] = ohlc.each_ref().map(|vs| vs.get(i).cloned().unwrap_or(f64::NAN));
+        if last != Some(key) {
+            for (vs, v) in ret.iter_mut().zip([o, h, l, c]) { vs.push_back(v); }
+            last = Some(key);
+            continue;
+        }
+
+        let [open, high, low, close] = ret.each_mut().map(|vs| vs.back_mut().unwrap());
+        if open.is_nan() { *open = o; } // the first non-NaN one
+        *high = high.max(h); // NaN is ignored in max/min
+        *low = low.min(l);
+        if !c.is_nan() { *close = c; } // the last non-NaN one
+    }
+    ret
+}
+
+// counts of values in each of `bins` bins evenly dividing lo ~ hi, for each series.
+// values out of range are ignored. counts are scaled into log10(1+count) if `log`.
+pub fn histogram(vss: &[(VecDeque<f64>,u32)], bins: usize, lo: f64, hi: f64, log: bool) -> Vec<(VecDeque<f64>,u32)> {
+    assert!(lo < hi && bins > 0);
+    vss.iter().map(|(vs,color)| {
+        let mut counts = VecDeque::from(vec![0f64; bins]);
+        for v in vs.iter().filter(|&&v| lo <= v && v <= hi) {
+            let i = ((v - lo) / (hi - lo) * bins as f64).floor() as usize;
+            counts[i.min(bins-1)] += 1.; // hi goes into the last bin
+        }
+        if log {
+            for c in counts.iter_mut() { *c = (1. + *c).log10(); }
+        }
+        (counts, *color)
+    }).collect()
+}
+
+// one line of blocks for each series, scaled into label_bot ~ label_top.
+// prefixed with the name and summary if requested.
+fn sparkline(vss: &[(VecDeque<f64>,u32)], cfg: Config) -> (String, usize) {
+    let intv = cfg.label_top - cfg.label_bot;
+
+    let fmt = |v: Option<f64>| cfg.format_value(v.unwrap_or(f64::NAN));
+    let prefixes = vss.iter().enumerate().map(|(k, (vs,_))| {
+        let vs = || vs.iter().cloned().take(cfg.width);
+        let mut fields = vec![];
+        if let Some(name) = cfg.names.get(k) { fields.push(name.clone()); }
+        if cfg.summary {
+            fields.push(fmt(vs().rfind(|v| !v.is_nan())));
+            fields.push(fmt(min_f64(vs())));
+            fields.push(fmt(max_f64(vs())));
+        }
+        fields
+    }).collect::<Vec<_>>();
+
+    // align each field across series, names to left and numbers to right
+    let widths = (0..prefixes.iter().map(|f| f.len()).max().unwrap_or(0)).map(|i| {
+        prefixes.iter().filter_map(|f| f.get(i)).map(|f| display_width(f)).max().unwrap_or(0)
+    }).collect::<Vec<_>>();
+    let named = !cfg.names.is_empty() as usize;
+
+    let mut ret = String::new();
+    for ((vs, color), fields) in vss.iter().zip(prefixes) {
+        for (i, field) in fields.iter().enumerate() {
+            let pad = " ".repeat(widths[i] - display_width(field));
+            match i.checked_sub(named) {
+                None    => write!(ret, "{}{} ", field, pad),
+                Some(0) => write!(ret, "last={}{} ", pad, field),
+                Some(1) => write!(ret, "min={}{} ", pad, field),
+                Some(_) => write!(ret, "max={}{} ", pad, field),
+            }.unwrap();
+        }
+
+        let blocks = vs.iter().cloned().take(cfg.width).map(|v| {
+            if v.is_nan() { return ' '; }
+            let level = if intv == 0. {3.} else { ((v - cfg.label_bot) / intv * 7.).round() };
+            cfg.bar_symbols[level.clamp(0., 7.) as usize] // '▁' to '█'
+        }).collect::<String>();
+        if *color == 9 {
+            writeln!(ret, "{}", blocks).unwrap();
+        } else {
+            writeln!(ret, "\x1b[3{}m{}\x1b[0m", color, blocks).unwrap();
+        }
+    }
+
+    (ret, vss.len())
+}
+
+// columns and rows of the terminal the output goes to, from COLUMNS and LINES if not a terminal
+pub fn terminal_size() -> Option<(usize, usize)> {
+    #[cfg(unix)]
+    {
+        let mut ws = libc::winsize { ws_row: 0, ws_col: 0, ws_xpixel: 0, ws_ypixel: 0 };
+        // SAFETY: TIOCGWINSZ only writes into the winsize given
+        if unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut ws) } == 0 && ws.ws_col > 0 && ws.ws_row > 0 {
+            return Some((ws.ws_col as usize, ws.ws_row as usize));
         }
-        write!(ret, "\n").unwrap();
     }
+    let env = |key| std::env::var(key).ok().and_then(|v| v.parse::<usize>().ok()).filter(|&n| n > 0);
+    env("COLUMNS").zip(env("LINES"))
+}
 
-    (ret, height + if cfg.x_label.is_none() {0} else {3}) // FIXME why 3 not 2???
+// number of terminal columns the string occupies. ansi escape sequences take no space,
+// east asian wide characters take two.
+pub fn display_width(s: &str) -> usize {
+    let mut width = 0;
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // skip until the final byte of CSI sequence, e.g. 'm' of "\x1b[31m"
+            if chars.next() == Some('[') {
+                for c in chars.by_ref() { if ('@'..='~').contains(&c) { break; } }
+            }
+            continue;
+        }
+        width += match c as u32 {
+            0x1100..=0x115F | 0x2E80..=0xA4CF | 0xAC00..=0xD7A3 | 0xF900..=0xFAFF |
+            0xFE30..=0xFE4F | 0xFF00..=0xFF60 | 0xFFE0..=0xFFE6 |
+            0x1F300..=0x1F64F | 0x1F900..=0x1F9FF | 0x20000..=0x3FFFD => 2,
+            _ => 1,
+        };
+    }
+    width
+}
+
+// joins rendered charts into a grid with `cols` panels per row, filling row by row.
+// each panel is padded into a rectangular block, so that panels of different size line up.
+pub fn grid(panels: &[String], cols: usize) -> (String, usize) {
+    assert!(cols > 0);
+
+    let blocks = panels.iter().map(|p| p.lines().collect::<Vec<_>>()).collect::<Vec<_>>();
+
+    // width of each column is the widest line among the panels in that column
+    let widths = (0..cols).map(|c| {
+        blocks.iter().skip(c).step_by(cols).flatten().map(|l| display_width(l)).max().unwrap_or(0)
+    }).collect::<Vec<_>>();
+
+    let mut ret = String::new();
+    let mut height = 0;
+    for (r, row) in blocks.chunks(cols).enumerate() {
+        if r > 0 { // a blank line between rows of panels
+            writeln!(ret).unwrap();
+            height += 1;
+        }
+
+        let row_height = row.iter().map(|b| b.len()).max().unwrap_or(0);
+        for y in 0..row_height {
+            let mut line = String::new();
+            for (c, block) in row.iter().enumerate() {
+                let l = block.get(y).copied().unwrap_or("");
+                line.push_str(l);
+                if c + 1 < row.len() { // no padding after the last panel
+                    let pad = widths[c] - display_width(l) + 2; // 2 for the gap between panels
+                    line.extend(std::iter::repeat_n(' ', pad));
+                }
+            }
+            writeln!(ret, "{}", line.trim_end()).unwrap();
+        }
+        height += row_height;
+    }
+
+    (ret, height)
 }
 
 
@@ -157,8 +967,13 @@ pub use clap::Parser;
 // TODO label on righthand?
 // TODO multiple labels?? <- multiple min-max?
 // TODO multiple plots?
-#[derive(Parser, Debug, Default)]
+#[derive(Parser, Debug, Default, Clone)]
 #[clap(name = "asciichart-cui")]
+// drawing modes, at most one of them. candles are given by columns, aggregation or both
+#[clap(group(clap::ArgGroup::new("mode").args(&["braille", "bars", "points", "histogram", "heatmap", "hbar", "sparkline", "style"])))]
+#[clap(group(clap::ArgGroup::new("candles").args(&["ohlc", "candle"]).multiple(true).conflicts_with("mode")))]
+// modes drawing without the plot area, for which the options on it are rejected
+#[clap(group(clap::ArgGroup::new("unplotted").args(&["sparkline", "heatmap", "hbar"])))]
 #[clap(author, version, about, long_about = None)] // read from Cargo.toml
 pub struct Args {
 
@@ -167,7 +982,7 @@ pub struct Args {
     pub xprec: Option<usize>,
 
     /// amount to increase x labels with. if omitted, x labels are not drawn.
-    #[clap(long, value_parser)]
+    #[clap(long, value_parser, conflicts_with_all=&["sparkline", "hbar"])]
     pub xstep: Option<f64>,
 
     /// value for the first x label
@@ -179,6 +994,17 @@ pub struct Args {
     #[clap(short='p', long, value_parser)]
     pub yprec: Option<usize>,
 
+    /// How to write y labels: "si" (1.2k), "bytes" (1.5KiB), "duration" of seconds (250ms),
+    /// "percent" of ratios (25%), "sci" (1.5e-7), "eng" (150.0e-9),
+    /// or a printf-like format with prefix and suffix, e.g. "$%.2f".
+    /// Plain labels of extreme magnitudes switch to "sci" if it is shorter, unless <YPREC> is given.
+    #[clap(long, value_parser=parse_yformat, default_value="plain")]
+    pub yformat: YFormat,
+
+    /// Put smaller values on top, for ranks, depths or latencies.
+    #[clap(long, value_parser, default_value_t=false, conflicts_with_all=&["bars", "histogram", "unplotted"])]
+    pub invert_y: bool,
+
     /// Maximum value of the vertical label.
     #[clap(short='M', long, value_parser)]
     pub ymax: Option<f64>,
@@ -189,14 +1015,22 @@ pub struct Args {
 
 
     /// # of datapoints to plot, trailing data will be ignored.
+    /// with --braille, --bars or --style step, this is the # of columns instead.
+    /// if not specified, the plot is fit into the terminal (or COLUMNS) showing the latest datapoints.
     #[clap(short, long, value_parser)]
     pub width: Option<usize>,
     // TODO used as an argument to interpolate feature in the future
 
-    /// # of rows in the plot. if not specified, height will be adjusted for integer-ranged labels.
+    /// # of rows in the plot. if not specified, height will be adjusted for integer-ranged labels,
+    /// lowered to fit in the terminal (or LINES) if too high.
     #[clap(short, long, value_parser)]
     pub height: Option<usize>,
 
+    /// Maximum # of rows in the plot when <HEIGHT> is not given, the terminal height (or 40) if omitted.
+    /// Labels are divided evenly instead of by integers if there would be more rows.
+    #[clap(long, value_parser)]
+    pub max_height: Option<usize>,
+
 
     /// Characters to be used for plot. Must be a string of width 10,
     /// where each characters are used for
@@ -218,7 +1052,155 @@ pub struct Args {
     //pub colors:
 
 
-    /// Repeat drawing the plot for each datarow.
+    /// Draw with braille dot patterns, fitting 2 datapoints in each column
+    /// and 4 levels in each row.
+    #[clap(long, value_parser, default_value_t=false)]
+    pub braille: bool,
+
+
+    /// Draw each datapoint as a vertical bar from zero, or from the bottom if zero is out of range.
+    /// Bars of multiple series are grouped side by side.
+    #[clap(long, value_parser, default_value_t=false)]
+    pub bars: bool,
+
+
+    /// Draw a marker for each datapoint without connecting them.
+    /// Each series gets its own marker, so they are distinguishable without colors.
+    #[clap(long, value_parser, default_value_t=false)]
+    pub points: bool,
+
+    /// Shade the area between each line and zero (or the bottom if zero is out of range)
+    /// with given character, '░' if omitted.
+    #[clap(long, value_parser, conflicts_with_all=&["braille", "bars", "histogram", "candles", "unplotted"])]
+    pub fill: Option<Option<char>>,
+
+    /// Draw the distribution of values of each series as bars, instead of the values in order.
+    /// <YMIN> and <YMAX> limit the range of values to count.
+    #[clap(long, value_parser, default_value_t=false)]
+    pub histogram: bool,
+
+    /// # of bins to divide the range of values with --histogram.
+    #[clap(long, value_parser=clap::builder::RangedU64ValueParser::<usize>::new().range(1..), default_value_t=10, requires="histogram")]
+    pub bins: usize,
+
+    /// Scale counts of --histogram logarithmically.
+    #[clap(long, value_parser, default_value_t=false, requires="histogram")]
+    pub log_count: bool,
+
+    /// Draw a row of shades for each series, labeled with its name.
+    /// Suitable for many series, which would be a mess of lines otherwise.
+    #[clap(long, value_parser, default_value_t=false)]
+    pub heatmap: bool,
+
+    /// Use a gradient of 256-color backgrounds instead of shade characters for --heatmap.
+    #[clap(long, value_parser, default_value_t=false, requires="heatmap")]
+    pub gradient: bool,
+
+    /// Draw a horizontal bar of the latest value for each series, labeled with its name.
+    /// Bars are scaled to fit in <WIDTH> columns, the terminal width (or 80) if omitted.
+    #[clap(long, value_parser, default_value_t=false)]
+    pub hbar: bool,
+
+    /// Draw candlesticks from 4 columns of open, high, low and close, e.g. "1,2,3,4".
+    /// Candles are green if they closed higher than opened, red otherwise.
+    #[clap(long, value_parser=parse_columns::<4>)]
+    pub ohlc: Option<[usize; 4]>,
+
+    /// Aggregate datapoints into a candle for every N datapoints, or every N seconds if given as "Ns".
+    /// Uses the first column as raw values unless --ohlc is given.
+    #[clap(long, value_parser=parse_candle)]
+    pub candle: Option<Bucket>,
+
+    /// Print a single line of blocks for each series, without axis and labels.
+    #[clap(long, value_parser, default_value_t=false)]
+    pub sparkline: bool,
+
+    /// Prefix each sparkline with last, min and max values of the series.
+    #[clap(long, value_parser, default_value_t=false, requires="sparkline")]
+    pub summary: bool,
+
+
+    /// Comma-separated names for each series, in the order of the input columns.
+    #[clap(long, value_parser, value_delimiter=',')]
+    pub names: Option<Vec<String>>,
+
+
+    /// Shade the band between 2 columns of lower and upper bounds, e.g. "2,3".
+    /// The bounds are not drawn as lines.
+    #[clap(long, value_parser=parse_columns::<2>, conflicts_with_all=&["braille", "bars", "histogram", "candles", "unplotted"])]
+    pub band: Option<[usize; 2]>,
+
+    /// Comma-separated column numbers to draw, all the others if omitted.
+    #[clap(long, value_parser, value_delimiter=',')]
+    pub line: Option<Vec<usize>>,
+
+    /// Draw a horizontal line at VALUE, given as VALUE[:COLOR][:LABEL], e.g. "95:red:SLO".
+    /// COLOR is one of black, red, green, yellow, blue, magenta, cyan and white.
+    /// The range of the plot is extended to include the line. Can be given multiple times.
+    /// With --histogram, VALUE is a count.
+    #[clap(long, value_parser=parse_hline, conflicts_with="unplotted")]
+    pub hline: Vec<(f64,u32,String)>,
+
+    /// Column of event labels, which are marked as vertical lines at the datapoint.
+    /// "-" means no event. The column is neither plotted nor counted in other column numbers.
+    /// Lines starting with "#!" are also events, e.g. "#! deploy v1.2", marked at the next datapoint.
+    #[clap(long, value_parser, conflicts_with_all=&["histogram", "candle", "unplotted"])]
+    pub event_column: Option<usize>,
+
+    /// Draw dotted grid lines at each row of y labels and at each tick of x labels,
+    /// or only one of them if "x" or "y" is given.
+    #[clap(long, value_enum, value_parser, conflicts_with="unplotted")]
+    pub grid: Option<Option<Grid>>,
+
+    /// Show a legend of series names and colors at "top", "bottom" or "right" of the plot.
+    /// Names are from --names or --header, column numbers otherwise.
+    /// A legend on the right goes to the bottom if there are more series than rows.
+    #[clap(long, value_enum, value_parser, conflicts_with_all=&["candles", "unplotted"])]
+    pub legend: Option<Option<Legend>>,
+
+    /// Show the last value of each series in the legend.
+    #[clap(long, value_parser, default_value_t=false, requires="legend")]
+    pub legend_values: bool,
+
+    /// Title centered above the plot.
+    #[clap(long, value_parser, conflicts_with="unplotted")]
+    pub title: Option<String>,
+
+    /// Caption centered under the x labels.
+    #[clap(long, value_parser, conflicts_with="unplotted")]
+    pub xlabel: Option<String>,
+
+    /// Caption written vertically left of the y labels.
+    #[clap(long, value_parser, conflicts_with="unplotted")]
+    pub ylabel: Option<String>,
+
+    /// Comma-separated column numbers to draw on top of the others, the topmost first.
+    /// Lines of different series crossing in a cell are drawn as a cross in the mix of their colors.
+    #[clap(long, value_parser, value_delimiter=',', conflicts_with_all=&["candles", "unplotted"])]
+    pub priority: Vec<usize>,
+
+    /// Round the labels to steps of 1, 2 or 5 times a power of 10,
+    /// with the # of rows closest to <HEIGHT>.
+    #[clap(long, value_parser, default_value_t=false)]
+    pub nice: bool,
+
+    /// Take names of series from the first line of input, unless --names is given.
+    #[clap(long, value_parser, default_value_t=false)]
+    pub header: bool,
+
+    /// How to connect datapoints in line mode. "step" holds each value for a column,
+    /// then jumps to the next one.
+    #[clap(long, value_enum, value_parser, default_value_t)]
+    pub style: Style,
+
+
+    /// Arrange the plot into a grid of charts, given as ROWSxCOLS (e.g. "2x2").
+    /// Each series gets its own chart, dealt into the panels in round-robin order.
+    #[clap(long, value_parser=parse_layout, conflicts_with_all=&["band", "candles"])]
+    pub layout: Option<(usize,usize)>,
+
+
+    /// Repeat drawing the plot for each datarow. The plot is re-fit and redrawn when the terminal is resized.
     #[clap(long, value_parser, default_value_t=false)]
     pub monitor: bool,
 
@@ -232,6 +1214,82 @@ fn validate_tileset(s :&str) -> Result<(), &'static str> {
     if s.len() == 13 {Ok(())} else {Err("should be of length 10")}
 }
 
+fn parse_columns<const N: usize>(s :&str) -> Result<[usize; N], String> {
+    let columns = s.split(',').map(|c| c.parse::<usize>().map_err(|e| e.to_string())).collect::<Result<Vec<_>,_>>()?;
+    match <[usize; N]>::try_from(columns) {
+        Ok(columns) if columns.iter().all(|&i| i > 0) => Ok(columns),
+        _ => Err(format!("should be {} column numbers starting from 1", N)),
+    }
+}
+
+fn parse_candle(s :&str) -> Result<Bucket, String> {
+    if let Some(secs) = s.strip_suffix('s') {
+        let secs = secs.parse::<f64>().map_err(|e| e.to_string())?;
+        if secs > 0. { Ok(Bucket::Seconds(secs)) } else { Err("should be positive".into()) }
+    } else {
+        let n = s.parse::<u64>().map_err(|e| e.to_string())?;
+        if n > 0 { Ok(Bucket::Samples(n)) } else { Err("should be positive".into()) }
+    }
+}
+
+fn parse_color(s :&str) -> Option<u32> {
+    let colors = ["black", "red", "green", "yellow", "blue", "magenta", "cyan", "white"];
+    colors.iter().position(|&c| c == s).map(|i| i as u32)
+}
+
+fn parse_hline(s :&str) -> Result<(f64,u32,String), String> {
+    let mut fields = s.split(':');
+    let value = fields.next().unwrap().parse::<f64>().map_err(|e| e.to_string())?;
+    let (mut color, mut label) = (9, String::new());
+    for field in fields {
+        match parse_color(field) {
+            Some(c) => color = c,
+            None => label = field.to_string(),
+        }
+    }
+    Ok((value, color, label))
+}
+
+fn parse_yformat(s :&str) -> Result<YFormat, String> {
+    match s {
+        "plain" => return Ok(YFormat::Plain),
+        "si" => return Ok(YFormat::Si),
+        "bytes" => return Ok(YFormat::Bytes),
+        "duration" => return Ok(YFormat::Duration),
+        "percent" => return Ok(YFormat::Percent),
+        "sci" => return Ok(YFormat::Scientific),
+        "eng" => return Ok(YFormat::Engineering),
+        _ => {},
+    }
+    // PREFIX%[.N]fSUFFIX, where "%%" is a literal '%'
+    let err = || format!("expected plain, si, bytes, duration, percent, sci, eng or a format like \"$%.2f\", got \"{}\"", s);
+    // the first '%' not in a pair of "%%", scanning from the left
+    let mut spec = 0;
+    loop {
+        spec += s[spec..].find('%').ok_or_else(err)?;
+        if !s[spec..].starts_with("%%") { break; }
+        spec += 2;
+    }
+    let (prefix, rest) = (&s[..spec], &s[spec+1..]);
+    let (prec, suffix) = match rest.strip_prefix('.') {
+        Some(rest) => {
+            let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+            (Some(rest[..digits].parse::<usize>().map_err(|_| err())?), &rest[digits..])
+        },
+        None => (None, rest),
+    };
+    let suffix = suffix.strip_prefix('f').ok_or_else(err)?;
+    Ok(YFormat::Custom(prefix.replace("%%", "%"), prec, suffix.replace("%%", "%")))
+}
+
+fn parse_layout(s :&str) -> Result<(usize,usize), String> {
+    let (rows, cols) = s.split_once('x').ok_or("should be of form ROWSxCOLS")?;
+    let rows = rows.parse::<usize>().map_err(|e| e.to_string())?;
+    let cols = cols.parse::<usize>().map_err(|e| e.to_string())?;
+    if rows == 0 || cols == 0 { return Err("rows and cols should be positive".into()); }
+    Ok((rows, cols))
+}
+
 // ignore NaN & +/-INF
 fn min_f64<T> (iter: T) -> Option<f64> where T: Iterator<Item=f64> {
     iter.filter(|v|!v.is_nan() && !v.is_infinite()).reduce(|a,v| if v<a {v} else {a})
@@ -240,9 +1298,52 @@ fn max_f64<T> (iter: T) -> Option<f64> where T: Iterator<Item=f64> {
     iter.filter(|v|!v.is_nan() && !v.is_infinite()).reduce(|a,v| if v>a {v} else {a})
 }
 
+// bottom, top and step of labels covering lo ~ hi, with steps of 1, 2 or 5 times a power of 10.
+// the step is chosen so that # of rows is closest to `height`, fewer rows if tied, and at most `max_rows`.
+fn nice_ticks(lo: f64, hi: f64, height: usize, max_rows: usize) -> (f64, f64, f64) {
+    let rough = (hi - lo) / height.saturating_sub(1).max(1) as f64;
+    let exp = rough.log10().floor() as i32;
+    let steps = (exp-1 ..= exp+1).flat_map(|e| [1., 2., 5.].map(|m| m * 10f64.powi(e)));
+    steps.map(|step| {
+        // small margins not to add a row for float errors
+        let bot = (lo / step + 1e-9).floor();
+        let top = (hi / step - 1e-9).ceil();
+        (bot * step, top * step, step, (top - bot) as usize + 1)
+    }).filter(|&(.., rows)| rows <= max_rows.max(2)) // the largest step always fits in `height` >= 2
+    .min_by_key(|&(.., rows)| (rows.abs_diff(height), rows))
+    .map(|(bot, top, step, _)| (bot, top, step)).unwrap()
+}
+
 impl Args {
+    pub fn mode(&self) -> Mode {
+        if self.braille { Mode::Braille }
+        else if self.bars || self.histogram { Mode::Bars }
+        else if self.sparkline { Mode::Sparkline }
+        else if self.points { Mode::Points }
+        else if self.heatmap { Mode::Heatmap }
+        else if self.hbar { Mode::Hbar }
+        else if self.ohlc.is_some() || self.candle.is_some() { Mode::Ohlc }
+        else if self.style == Style::Step { Mode::Step }
+        else { Mode::Line }
+    }
+
+    // names for the series at given indices, or empty if not named.
+    // missing names are filled with the column number.
+    pub fn series_names(&self, indices: impl Iterator<Item=usize>) -> Vec<String> {
+        match &self.names {
+            None => vec![],
+            Some(names) => indices.map(|i| names.get(i).cloned().unwrap_or((i+1).to_string())).collect(),
+        }
+    }
+
+    // positions of series with priority among the series at `indices`, the topmost first
+    pub fn series_priority(&self, indices: impl Iterator<Item=usize>) -> Vec<usize> {
+        let indices = indices.collect::<Vec<_>>();
+        self.priority.iter().filter_map(|&c| indices.iter().position(|&i| i+1 == c)).collect()
+    }
+
     // handles generates configs, calculates defaults that are data-related
-    pub fn gen_config(&self, vss: &Vec<(VecDeque<f64>,u32)>) -> Option<Config> {
+    pub fn gen_config(&self, vss: &[(VecDeque<f64>,u32)]) -> Option<Config> {
       // FIXME is optional needed?
 
         let (v_bot, v_top) = {
@@ -251,17 +1352,63 @@ impl Args {
             let max = max_f64(vss.iter().map(|(vs,_)| max_f64(vs.iter().cloned()).unwrap_or(nan)));
 
             if let (Some(min), Some(max)) = (min, max) {
+                // include horizontal lines, so that they are always visible. with histogram, they are of counts
+                let hlines = self.hline.iter().map(|h| h.0).filter(|_| !self.histogram);
+                let min = min_f64(hlines.clone().chain([min])).unwrap();
+                let max = max_f64(hlines.chain([max])).unwrap();
                 (self.ymin.unwrap_or(min), self.ymax.unwrap_or(max))
             } else {
                 return None;
             }
         };
 
-        let width = self.width.unwrap_or(vss.iter().map(|vs| vs.0.len()).min().unwrap_or(0));
+        // with histogram, the range of values is for the bins, and counts are plotted instead
+        let histogram = if self.histogram {
+            // widen single-valued range, so that bins have non-zero width
+            let (lo, hi) = if v_bot < v_top {(v_bot, v_top)} else {(v_bot - 0.5, v_top + 0.5)};
+            // nothing to count if <YMIN> is above <YMAX> or all the values, or into no bins
+            if lo >= hi || self.bins == 0 { return None; }
+            Some((self.bins, lo, hi, self.log_count))
+        } else { None };
+        // log-scaled counts are less than 1 for a few values, too narrow for integer rows
+        let log_count = histogram.is_some_and(|(.., log)| log);
+        // horizontal lines at counts are scaled as the counts are
+        let hlines = self.hline.iter().map(|(v, color, label)| {
+            (if log_count {(1. + v).log10()} else {*v}, *color, label.clone())
+        }).collect::<Vec<_>>();
+        let (v_bot, v_top) = if let Some((bins, lo, hi, log)) = histogram {
+            let counts = crate::histogram(vss, bins, lo, hi, log);
+            let counts = counts.iter().flat_map(|(cs,_)| cs.iter().cloned());
+            let min = min_f64(hlines.iter().map(|h| h.0).chain([0.])).unwrap();
+            (min, max_f64(hlines.iter().map(|h| h.0).chain(counts)).unwrap_or(0.))
+        } else {
+            (v_bot, v_top)
+        };
+
+        let width = if let Some((bins, ..)) = histogram {
+            self.mode().columns(bins, vss.len())
+        } else if self.hbar {
+            self.width.unwrap_or(80)
+        } else {
+            self.width.unwrap_or({
+                let len = vss.iter().map(|vs| vs.0.len()).min().unwrap_or(0);
+                self.mode().columns(len, vss.len())
+            })
+        };
 
         let v_interval = v_top - v_bot; // >= 0
+        // integer labels are kept unless there are too many rows of them
+        let max_height = self.max_height.unwrap_or(DEFAULT_MAX_HEIGHT).max(2);
+        let integer_rows = ((v_top.ceil() - v_bot.floor()) as usize).saturating_add(1);
+        let integer = self.height.is_none() && integer_rows <= max_height && !log_count;
         let height = if v_interval == 0. {1} else { // force height to 1 if single-valued
-            self.height.unwrap_or(1 + v_interval.floor() as usize)
+            self.height.unwrap_or_else(|| {
+                // a row for each count as without log scale, but at most 4 rows a decade if log-scaled
+                let rows = if log_count {
+                    (10f64.powf(v_interval) - 1.).round().min((4. * v_interval).ceil())
+                } else {v_interval.floor()};
+                (rows as usize).saturating_add(1).min(max_height)
+            })
         }; // >= 1
 
         let (label_bot, label_top, v_step) = if height == 1 {
@@ -270,38 +1417,113 @@ impl Args {
             // to indicate the range of values coverd in the plot
             let v_step = v_interval * 1.5; // *1.5 for generouse error range
             (mid, mid, v_step)
-        } else if self.height.is_none() {
+        } else if self.nice && !log_count {
+            // rounding may add rows, which must not exceed the cap unless the height is given
+            nice_ticks(v_bot, v_top, height, if self.height.is_none() {max_height} else {usize::MAX})
+        } else if integer {
             // use integer mode when height is not specified
             (v_bot.floor(), v_top.ceil(), 1.)
         } else {
-            (v_bot, v_top, v_interval / (height-1) as f64)
+            // dividing each, not to overflow for extreme ranges
+            (v_bot, v_top, v_top / (height-1) as f64 - v_bot / (height-1) as f64)
         };
 
         let label_precision = self.yprec.unwrap_or({
+            // counts differ the least between the bottom rows, if log-scaled
+            let v_step = if log_count {10f64.powf(v_step) - 1.} else {v_step};
             let signum = if v_step != 0. {v_step} else if label_bot != 0. {label_bot} else {1.};
             let prec = 1 - signum.log10().floor() as i32;
-            (if self.height.is_none() {0} else {1}).max(prec) as usize
-            // force prec >= 1 unless height=None (integer mode)
+            let prec = if self.nice && height > 1 {
+                // nice steps have a single significant digit
+                0.max(prec - 1) as usize
+            } else {
+                (if self.height.is_none() {0} else {1}).max(prec) as usize
+                // force prec >= 1 unless height=None (integer mode)
+            };
+            match self.yformat {
+                // of scaled values, not of the step
+                YFormat::Si | YFormat::Bytes | YFormat::Duration => 1,
+                // of hundredfold values
+                YFormat::Percent => prec.saturating_sub(2),
+                _ => prec,
+            }
         });
 
+        // enough significant digits to tell the rows apart in scientific notation
+        let magnitude = label_bot.abs().max(label_top.abs());
+        let sci_precision = if v_step != 0. && magnitude != 0. {
+            (magnitude.log10().floor() - v_step.log10().floor()).max(0.) as usize
+        } else {1};
+        // plain labels of extreme magnitudes are too long, so in scientific notation if it's shorter
+        let y_format = match self.yformat {
+            YFormat::Plain if self.yprec.is_none() && !log_count && (magnitude >= 1e7 || label_precision > 6) => {
+                let longest = |format: &dyn Fn(f64) -> String| {
+                    [label_bot, label_top].iter().map(|&v| format(v).len()).max().unwrap()
+                };
+                let sci = longest(&|v| format!("{:.1$e}", v, sci_precision));
+                let plain = longest(&|v| format!("{:.1$}", v, label_precision));
+                if sci < plain {YFormat::Scientific} else {YFormat::Plain}
+            },
+            ref y_format => y_format.clone(),
+        };
+        let label_precision = match (&y_format, self.yprec) {
+            (YFormat::Scientific | YFormat::Engineering, None) => sci_precision,
+            _ => label_precision,
+        };
+
+        // with histogram, x labels are the lower edges of bins
+        let x_axis = if let Some((bins, lo, hi, _)) = histogram {
+            Some((lo, (hi - lo) / bins as f64))
+        } else {
+            self.xstep.map(|xstep| (self.xmin, xstep))
+        };
+
         // x_start, x_step, x_prec, x_interval
-        let x_label = self.xstep.map(|xstep| {
+        let x_label = x_axis.map(|(xmin, xstep)| {
             let xprec = self.xprec.unwrap_or({
                 let signum = if xstep != 0. {xstep} else {xstep.abs()};
                 0f64.max(-signum.log10().floor()) as usize
             });
-            let xint = {
-                let body = (self.xmin + 10000.*xstep).abs().log10().ceil() as usize;
-                println!("{} {}", body, xprec);
+            let xint = if let Some((bins, ..)) = histogram {
+                // just wide enough for the edge labels, aligned to the start of bins
+                let group = self.mode().columns(2, vss.len()) - self.mode().columns(1, vss.len());
+                let body = [xmin, xmin + xstep * bins as f64].iter()
+                    .map(|x| format!("{:.1$}", x, xprec).len()).max().unwrap();
+                (body + 1).div_ceil(group) * group
+            } else {
+                let body = (xmin + 10000.*xstep).abs().log10().ceil() as usize;
                 2 * (body + 1 + xprec)
             };
-            (self.xmin, xstep, xprec, xint)
+            (xmin, xstep, xprec, xint)
         });
 
         let mut cfg = Config {
-            symbols: if self.ascii {ASCII_SYMBOLS} else {UNICODE_SYMBOLS}, width: width,
-            label_bot: label_bot, label_top: label_top, v_step: v_step,
-            label_precision: label_precision, x_label: x_label,
+            symbols: if self.ascii {ASCII_SYMBOLS} else {UNICODE_SYMBOLS},
+            bar_symbols: if self.ascii {ASCII_BARS} else {UNICODE_BARS},
+            hbar_symbols: if self.ascii {ASCII_HBARS} else {UNICODE_HBARS},
+            candle_symbols: if self.ascii {ASCII_CANDLES} else {UNICODE_CANDLES},
+            point_symbols: if self.ascii {ASCII_POINTS} else {UNICODE_POINTS},
+            mode: self.mode(), width,
+            label_bot, label_top, v_step,
+            label_precision, y_format, log_scale: log_count, invert: self.invert_y, x_label,
+            overflow_symbols: if self.ascii {['^','v']} else {['▲','▼']},
+            names: self.series_names(0..vss.len()), summary: self.summary,
+            fill: self.fill.map(|c| c.unwrap_or(if self.ascii {':'} else {'░'})),
+            band: self.band.map(|_| if self.ascii {':'} else {'░'}),
+            hlines, dash_symbol: if self.ascii {'-'} else {'╌'},
+            events: vec![], event_symbol: if self.ascii {':'} else {'┊'},
+            grid: match self.grid {
+                None => (false, false),
+                Some(Some(Grid::Y)) => (true, false),
+                Some(Some(Grid::X)) => (false, true),
+                Some(_) => (true, true),
+            },
+            grid_symbol: if self.ascii {'.'} else {'·'},
+            legend: self.legend.map(|pos| (pos.unwrap_or_default(), self.legend_values)),
+            swatch_symbol: if self.ascii {'#'} else {'■'},
+            title: self.title.clone(), x_caption: self.xlabel.clone(), y_caption: self.ylabel.clone(),
+            priority: self.series_priority(0..vss.len()), cross_symbol: if self.ascii {'+'} else {'┼'},
+            histogram, gradient: self.gradient,
         };
 
         if ! self.ascii {
@@ -329,13 +1551,16 @@ mod tests {
 
     macro_rules! toSeries {
         ([$($v:tt),*]) => {
-            vec![$(toF64!($v),)*]
+            std::collections::VecDeque::from(vec![$(toF64!($v),)*])
         };
     }
 
     macro_rules! set_arg {
         (cfg, $arg:ident, $key:ident, $val:expr) => {
         };
+        (flag, $arg:ident, $key:ident, $val:expr) => {
+            $arg.$key = $val;
+        };
         (arg, $arg:ident, $key:ident, $val:expr) => {
             $arg.$key = Some($val);
         };
@@ -347,6 +1572,8 @@ mod tests {
         };
         (arg, $cfg:ident, $key:ident, $val:expr) => {
         };
+        (flag, $cfg:ident, $key:ident, $val:expr) => {
+        };
     }
 
     macro_rules! graph_eq {
@@ -360,7 +1587,7 @@ mod tests {
           #[allow(unused_mut)]
           let mut cfg = arg.gen_config(&vss).unwrap();
           $(set_cfg!($ctn, cfg, $key, $val);)*
-          let ret = crate::plot(&vss, cfg);
+          let (ret, _) = crate::plot(&vss, cfg);
           let ref_line_start = if $rhs.chars().next() == Some('\n') {1} else {0};
           for (line1, line2) in std::iter::zip(ret.lines(), $rhs[ref_line_start..].lines()) {
             let result = panic::catch_unwind(|| { // this works like try: clause
@@ -391,10 +1618,15 @@ mod tests {
     graph_eq!(mountain_valley ? arg.height=4 ;
               [10,20,30,40,30,20,10], [40,30,20,10,20,30,40] => "
  40.0 ┼╮ ╭╮ ╭
- 30.0 ┤╰╮╯╰╭╯
- 20.0 ┤╭╰╮╭╯╮
+ 30.0 ┤╰┼╯╰┼╯
+ 20.0 ┤╭┼╮╭┼╮
  10.0 ┼╯ ╰╯ ╰ ");
 
+    graph_eq!(priority ? flag.points=true, flag.priority=vec![1] ; [1,2,3], [1,2,1] => "
+ 3.0 ┤ ●
+ 2.0 ┤●
+ 1.0 ● ×");
+
     // `cfg` is an optional dictionary of various parameters to tune the appearance
     // of the chart. `min` and `max` will clamp the y-axis and all values:
     graph_eq!(ymin0 ? arg.ymin=0. ; [1,2,3,4,_,4,3,2,1] => "
@@ -407,11 +1639,11 @@ mod tests {
     graph_eq!(ymin1 ? arg.ymin=2. ; [1,2,3,4,_,4,3,2,1] => "
  4.0 ┤  ╭╴╶╮
  3.0 ┤ ╭╯  ╰╮
- 2.0 ┤╭╯    ╰╮ ");
+ 2.0 ▼╭╯    ╰▼");
 
     graph_eq!(ymin2 ? arg.ymin=2., arg.ymax=3. ; [1,2,3,4,_,4,3,2,1] => "
- 3.0 ┤ ╭╯  ╰╮
- 2.0 ┤╭╯    ╰╮ ");
+ 3.0 ┤ ╭▲ ▲╰╮
+ 2.0 ▼╭╯    ╰▼");
 
     // `height` specifies the number of rows the graph should occupy. It can be
     // used to scale down a graph with large data values:
@@ -503,7 +1735,7 @@ mod tests {
  -1.0 ┤   ││
  -2.0 ┤   ╰╯                 ");
 
-    graph_eq!(test_six ? arg.yprec = 2 ; [0.2,0.1,0.2,2,(-0.9),0.7,1.28,0.3,0.7,0.4,0.5] => "
+    graph_eq!(test_six ? arg.height=3, arg.yprec=2 ; [0.2,0.1,0.2,2,(-0.9),0.7,1.28,0.3,0.7,0.4,0.5] => "
   2.00 ┤  ╭╮ ╭╮
   0.55 ┼──╯│╭╯╰───
  -0.90 ┤   ╰╯      ");
@@ -563,6 +1795,19 @@ mod tests {
  0.16 ┤   ╰╯                   ╰╯    ");
  */
 
+    graph_eq!(nice ? arg.height=11, flag.nice=true ; [
+                0,0,0,0,1.5,0,0,(-0.5),9, (-3),0,0,1,2,1,0,0,0,0,
+				0,0,0,0,1.5,0,0,(-0.5),8, (-3),0,0,1,2,1,0,0,0,0,
+				0,0,0,0,1.5,0,0,(-0.5),10,(-3),0,0,1,2,1,0,0,0,0] => "
+ 10 ┤       ╭╮                                    ╭╮
+  8 ┤       ││                 ╭╮                 ││
+  6 ┤       ││                 ││                 ││
+  4 ┤       ││                 ││                 ││
+  2 ┤   ╭╮  ││  ╭──╮       ╭╮  ││  ╭──╮       ╭╮  ││  ╭──╮
+  0 ┼───╯╰──╯│╭─╯  ╰───────╯╰──╯│╭─╯  ╰───────╯╰──╯│╭─╯  ╰───
+ -2 ┤        ╰╯                 ╰╯                 ╰╯
+ -4 ┤ ");
+
     graph_eq!(test_twelve ? arg.height=11 ; [
                 0,0,0,0,1.5,0,0,(-0.5),9, (-3),0,0,1,2,1,0,0,0,0,
 				0,0,0,0,1.5,0,0,(-0.5),8, (-3),0,0,1,2,1,0,0,0,0,
@@ -638,5 +1883,242 @@ mod tests {
  37.46 ┤                                                     ╰╯ │││
  36.45 ┤                                                        ╰╯╰ ");
 
-}
+    graph_eq!(braille ? flag.braille=true, arg.height=2, arg.yprec=0 ; [0,1,2,3,4,5,6,7] => "
+ 7 ┤  ⣀⠔
+ 0 ┼⠒⠉  ");
+
+    graph_eq!(bars ? flag.bars=true, arg.yprec=0 ; [(-2),(-1),0,1,2,3] => "
+  3 ┤     ▄
+  2 ┤    ▄█
+  1 ┤   ▄██
+  0 ┤▄▄ ▀▀▀
+ -1 ┤█▀
+ -2 ┤▀     ");
+
+    graph_eq!(bars_constant ? flag.bars=true ; [5] => "
+ 5.0 ┤▄");
+
+    graph_eq!(bars_constants ? flag.bars=true ; [3,3,3] => "
+ 3.0 ┤▄▄▄");
+
+    graph_eq!(bars_grouped ? flag.bars=true, arg.yprec=0 ; [1,2], [2,1] => "
+ 2 ┤ ▄ ▄
+ 1 ┤▄█ █▄ ");
+
+    graph_eq!(step ? flag.style=crate::Style::Step ; [1,2,2,1] => "
+ 2.0 ┤ ╭───╮
+ 1.0 ┼─╯   ╰─ ");
+
+    graph_eq!(band ? flag.band=Some([1,2]) ; [1,1,1], [3,4,3], [2,3,2] => "
+ 4.0 ┤░
+ 3.0 ┤╭╮
+ 2.0 ┼╯╰
+ 1.0 ┤░░ ");
+
+    graph_eq!(hline ? flag.hline=vec![(4., 9, "SLO".into())] ; [1,2,3] => "
+ 4.0 ┤╌╌ SLO
+ 3.0 ┤ ╭
+ 2.0 ┤╭╯
+ 1.0 ┼╯ ");
+
+    graph_eq!(grid ? flag.grid=Some(None), flag.hline=vec![(2., 9, "".into())] ; [1,3,3,1] => "
+ 3.0 ┤╭─╮
+ 2.0 ┤│╌│
+ 1.0 ┼╯·╰");
+
+    graph_eq!(legend ? flag.legend=Some(None), flag.legend_values=true, flag.names=Some(vec!["up".into()]) ; [1,2,3] => "
+ 3.0 ┤ ╭  ■ up 3.0
+ 2.0 ┤╭╯
+ 1.0 ┼╯ ");
+
+    graph_eq!(legend_overflow ? flag.legend=Some(Some(crate::Legend::Right)) ; [1,2], [2,1], [1,1] => "
+ 2.0 ┼┼
+ 1.0 ┼┼
+      ■ 1  ■ 2  ■ 3");
+
+    graph_eq!(captions ? flag.title=Some("rise".into()), flag.xlabel=Some("t".into()), flag.ylabel=Some("v".into()), flag.xstep=Some(1.) ; [1,2,3] => "
+   rise
+   3.0 ┤ ╭
+ v 2.0 ┤╭╯
+   1.0 ┼╯
+       ╞═══
+       0
+    t");
+
+    #[test]
+    fn modes() {
+        use clap::{CommandFactory, Parser};
+        crate::Args::command().debug_assert();
+        let parse = |args: &[&str]| crate::Args::try_parse_from([&["asciichart"], args].concat());
+        assert!(parse(&["--ohlc", "1,2,3,4", "--candle", "2"]).is_ok());
+        assert!(parse(&["--style", "step", "--bars"]).is_err());
+        assert!(parse(&["--candle", "2", "--points"]).is_err());
+        assert!(parse(&["--fill", "--sparkline"]).is_err());
+        assert!(parse(&["--legend", "--heatmap"]).is_err());
+    }
+
+    #[test]
+    fn parse_yformat() {
+        use crate::YFormat::Custom;
+        assert_eq!(crate::parse_yformat("$%.2f"), Ok(Custom("$".into(), Some(2), "".into())));
+        assert_eq!(crate::parse_yformat("%f%%"), Ok(Custom("".into(), None, "%".into())));
+        assert_eq!(crate::parse_yformat("%%%.1f"), Ok(Custom("%".into(), Some(1), "".into())));
+        assert!(crate::parse_yformat("100%%").is_err());
+    }
+
+    graph_eq!(yformat ? arg.height=3, flag.yformat=crate::parse_yformat("si").unwrap() ; [500,1000,1500] => "
+  1.5k ┤ ╭
+  1.0k ┤╭╯
+ 500.0 ┼╯");
+
+    #[test]
+    fn nice_max_height() {
+        let vss = vec![(std::collections::VecDeque::from(vec![3., 97.]), 9)];
+        let cfg = crate::Args { nice: true, max_height: Some(10), ..Default::default() }.gen_config(&vss).unwrap();
+        let (ret, height) = crate::plot(&vss, cfg);
+        assert_eq!(ret, " 100 ┤╭\n  80 ┤│\n  60 ┤│\n  40 ┤│\n  20 ┤│\n   0 ┼╯\n");
+        assert_eq!(height, 6);
+    }
 
+    graph_eq!(scientific ? arg.height=3 ; [0.0000001,0.0000003,0.0000002] => "
+ 3e-7 ┤╭╮
+ 2e-7 ┤│╰
+ 1e-7 ┼╯");
+
+    graph_eq!(scientific_near ? ; [10000000,10000002,10000004] => "
+ 10000004.0 ┤ ╭
+ 10000003.0 ┤ │
+ 10000002.0 ┤╭╯
+ 10000001.0 ┤│
+ 10000000.0 ┼╯");
+
+    graph_eq!(zeros ? ; [0,0] => "
+ 0.0 ┼─");
+
+    graph_eq!(invert_y ? flag.invert_y=true ; [1,2,3,1] => "
+ 1.0 ┼╮ ╭
+ 2.0 ┤╰╮│
+ 3.0 ┤ ╰╯");
+
+    graph_eq!(overflow ? arg.ymax=3., arg.ymin=1. ; [2,5,2,0,2] => "
+ 3.0 ┤▲│
+ 2.0 ┼╯╰╮╭
+ 1.0 ┤  ▼│");
+
+    graph_eq!(max_height ? flag.max_height=Some(3) ; [0,100,50] => "
+ 100 ┤╭╮
+  50 ┤│╰
+   0 ┼╯");
+
+    #[test]
+    fn zero_width() {
+        let vss = vec![(std::collections::VecDeque::from(vec![1.]), 9)];
+        let mut cfg = crate::Args::default().gen_config(&vss).unwrap();
+        cfg.width = 0;
+        assert_eq!(crate::plot(&vss, cfg), (" 1.0 ┼\n".to_string(), 1));
+    }
+
+    graph_eq!(points ? flag.points=true ; [1,2,3,2,1], [3,2,1,1,1] => "
+ 3.0 × ●
+ 2.0 ┤× ●
+ 1.0 ● ××× ");
+
+    graph_eq!(fill ? arg.fill=None ; [1,3,2] => "
+ 3.0 ┤╭╮
+ 2.0 ┤│╰
+ 1.0 ┼╯░ ");
+
+    graph_eq!(histogram_hline ? flag.histogram=true, flag.bins=3, flag.hline=vec![(3., 9, "max".into())] ; [1,2,3,3] => "
+ 3.0 ┤╌╌╌ max
+ 2.0 ┤  ▄
+ 1.0 ┤▄▄█
+ 0.0 ┤▀▀▀");
+
+    graph_eq!(histogram_log ? flag.histogram=true, flag.bins=3, flag.log_count=true ; [1,1,1,2,3,3] => "
+ 3.00 ┤▄
+ 1.52 ┤█ ▇
+ 0.59 ┤███
+ 0.00 ┤▀▀▀");
+
+    #[test]
+    fn histogram_empty_range() {
+        let vss = vec![(std::collections::VecDeque::from(vec![1., 2., 3.]), 9)];
+        let args = crate::Args { histogram: true, ymin: Some(5.), ..Default::default() };
+        assert!(args.gen_config(&vss).is_none());
+    }
+
+    graph_eq!(histogram ? flag.histogram=true, flag.bins=3, arg.yprec=0 ; [1,2,2,3,3,3,4] => "
+ 4 ┤  ▄
+ 3 ┤  █
+ 2 ┤ ▄█
+ 1 ┤▄██
+ 0 ┤▀▀▀
+   ╞╤═╤═
+    1 3");
+
+    graph_eq!(heatmap ? flag.heatmap=true, flag.xstep=Some(1.) ;
+              [0,1,2,3,4,5,6,7,8,9], [9,_,7,6,5,4,3,2,1,0] => "
+ 1 ┤ .:-=+*#%@
+ 2 ┤@ #*+=-:.
+   ╞╤═════════╤
+    0         10");
+
+    graph_eq!(hbar ? flag.hbar=true, arg.width=20, flag.names=Some(vec!["a".into(), "bb".into()]) ;
+              [1,2,3,4], [2,2,_] => "
+  a ┤███████████ 4.0
+ bb ┤█████▌      2.0");
+
+    graph_eq!(ohlc ? flag.ohlc=Some([1,2,3,4]) ; [2], [3], [1], [3] => "
+ 3.0 ┤\x1b[32m┃\x1b[0m
+ 2.0 ┤\x1b[32m┃\x1b[0m
+ 1.0 ┤\x1b[32m│\x1b[0m ");
+
+    #[test]
+    fn candles() {
+        let vs = std::collections::VecDeque::from(vec![3., 5., f64::NAN, 1., 2., 4.]);
+        let keys = std::collections::VecDeque::from(vec![0, 0, 0, 1, 1, 2]);
+        let ohlc = crate::candles(&[vs.clone(), vs.clone(), vs.clone(), vs], &keys);
+        assert_eq!(ohlc, [vec![3., 1., 4.], vec![5., 2., 4.], vec![3., 1., 4.], vec![5., 2., 4.]].map(std::collections::VecDeque::from));
+    }
+
+    #[test]
+    fn events() {
+        let vss = vec![(std::collections::VecDeque::from(vec![1., 2., 3., 2.]), 9)];
+        let mut cfg = crate::Args::default().gen_config(&vss).unwrap();
+        cfg.events = vec![(2, "deploy".into()), (0, "on axis".into())];
+        let (ret, height) = crate::plot(&vss, cfg);
+        assert_eq!(ret, "     o deploy\n 3.0 ┊ ╭╮\n 2.0 ┊╭╯╰\n 1.0 ┼╯┊ \n");
+        assert_eq!(height, 4);
+    }
+
+    #[test]
+    fn sparkline() {
+        let vss = vec![
+            (std::collections::VecDeque::from(vec![0., 1., 2., f64::NAN, 4., 7.]), 9),
+            (std::collections::VecDeque::from(vec![7., 6., 5., 4., 3., 2.]), 9),
+        ];
+        let arg = crate::Args {
+            sparkline: true, summary: true,
+            names: Some(vec!["cpu".to_string()]),
+            ..Default::default()
+        };
+        let (ret, height) = crate::plot(&vss, arg.gen_config(&vss).unwrap());
+        assert_eq!(ret, "cpu last=7.0 min=0.0 max=7.0 ▁▂▃ ▅█\n2   last=2.0 min=2.0 max=7.0 █▇▆▅▄▃\n");
+        assert_eq!(height, 2);
+    }
+
+    #[test]
+    fn grid_pads_panels() {
+        // colored and wide characters should not break the alignment
+        let panels = [
+            "ab\n\x1b[31mc\x1b[0m\n".to_string(),
+            "한\n".to_string(),
+            "d\n".to_string(),
+        ];
+        let (ret, height) = crate::grid(&panels, 2);
+        assert_eq!(ret, "ab  한\n\x1b[31mc\x1b[0m\n\nd\n");
+        assert_eq!(height, 4);
+        assert_eq!(crate::display_width("\x1b[31m─\x1b[0m한"), 3);
+    }
+
+}
@@ -1,32 +1,119 @@
 
-use asciichart_cli::{plot, Args, Parser};
+use asciichart_cli::{candles, display_width, grid, plot, terminal_size, Args, Bucket, Mode, Parser};
+use clap::{CommandFactory, ErrorKind};
 use std::collections::VecDeque;
 use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Duration;
+
+// set when the terminal is resized, to redraw the whole screen in monitor mode
+static RESIZED: AtomicBool = AtomicBool::new(false);
+// how often to check for resizes while waiting for the next datarow
+const RESIZE_POLL: Duration = Duration::from_millis(100);
+
+#[cfg(unix)]
+extern "C" fn on_resize(_: libc::c_int) {
+    RESIZED.store(true, Ordering::Relaxed);
+}
 
 fn main() {
-    let args = Args::parse();
+    let mut args = Args::parse();
 
     if let Some(ref demos) = args.demo {
         let vss = demo_data(demos);
-        print!("{}", plot(&vss, args.gen_config(&vss).unwrap()).0);
+        // demo data has no arrival time, so bucket by the datapoint count only
+        let len = vss.iter().map(|(vs,_)| vs.len()).max().unwrap_or(0) as u64;
+        let keys = (0..len).map(|i| match args.candle {
+            Some(Bucket::Samples(n)) => i / n,
+            _ => i,
+        }).collect();
+        print!("{}", render(&args, &vss, &keys, &VecDeque::new(), args.xmin, terminal_size()).unwrap().0);
         return;
     }
 
+    #[cfg(unix)]
+    if args.monitor {
+        // SAFETY: the handler only stores to an atomic
+        unsafe { libc::signal(libc::SIGWINCH, on_resize as extern "C" fn(libc::c_int) as libc::sighandler_t); }
+    }
+
     let mut vss = vec![];
     let mut datacnt = 0;
 
     let mut last_height = 1;
     let mut x_start = args.xmin;
 
-    let width = args.width.unwrap_or(80);
+    // bucket of each datapoint to be aggregated into a candle
+    let mut keys = VecDeque::new();
+    let mut rowcnt = 0;
+    let start = std::time::Instant::now();
 
-    loop {
+    // event label of each datapoint, empty if none
+    let mut events = VecDeque::new();
+    let mut pending_event = String::new();
+
+    // lines are read on another thread, so that a resize is redrawn without waiting for input
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || loop {
         let mut line = String::new();
-        std::io::stdin().read_line(&mut line).unwrap();
+        let eof = std::io::stdin().read_line(&mut line).unwrap() == 0;
+        if eof || sender.send(line).is_err() { break; }
+    });
+
+    loop {
+        let received = if args.monitor {
+            receiver.recv_timeout(RESIZE_POLL)
+        } else {
+            receiver.recv().map_err(|_| RecvTimeoutError::Disconnected)
+        };
+        let line = match received {
+            Ok(line) => line,
+            Err(RecvTimeoutError::Timeout) => {
+                // re-fit the last chart to the new terminal size
+                if RESIZED.load(Ordering::Relaxed) {
+                    let ret = render(&args, &vss, &keys, &events, x_start, terminal_size());
+                    overwrite(ret, &mut last_height);
+                }
+                continue;
+            },
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
         if line.trim().is_empty() { break; }
+
+        // "#!" lines mark an event at the next datapoint
+        if let Some(label) = line.trim().strip_prefix("#!") {
+            pending_event = label.trim().to_string();
+            continue;
+        }
+
+        // names of series, given in the first line
+        if args.header {
+            args.header = false;
+            let fields = line.split_whitespace().enumerate()
+                .filter(|(j,_)| args.event_column != Some(j+1)).map(|(_,field)| field.to_string());
+            args.names = args.names.or(Some(fields.collect()));
+            continue;
+        }
         datacnt += 1;
 
-        for (i,field) in line.split_whitespace().enumerate() {
+        // # of datapoints to keep, which differs from # of columns in braille or bars mode
+        let width = if args.histogram {
+            args.width.unwrap_or(usize::MAX) // count all the data unless limited
+        } else {
+            // as many as the terminal can show, cut to fit when drawn
+            let cols = terminal_size().map(|(cols, _)| cols);
+            args.mode().datapoints(args.width.or(cols).unwrap_or(80), vss.len())
+        };
+
+        let mut event = std::mem::take(&mut pending_event);
+        let mut fieldcnt = 0;
+        for (j,field) in line.split_whitespace().enumerate() {
+            if args.event_column == Some(j+1) {
+                if field != "-" { event = field.to_string(); }
+                continue;
+            }
+            let i = fieldcnt;
             if vss.len() <= i {
                 vss.push((VecDeque::from(vec![f64::NAN; datacnt-1]), 1+i as u32));
             }
@@ -36,43 +123,176 @@ fn main() {
             } else {
                 vss[i].0.push_back(f64::NAN);
             }
+            fieldcnt += 1;
+        }
+        // keep the series aligned even if some fields are missing
+        for (vs,_) in vss.iter_mut().skip(fieldcnt) {
+            vs.push_back(f64::NAN);
+        }
+
+        events.push_back(event);
+        keys.push_back(match args.candle {
+            Some(Bucket::Samples(n)) => rowcnt / n,
+            Some(Bucket::Seconds(secs)) => (start.elapsed().as_secs_f64() / secs) as u64,
+            None => rowcnt,
+        });
+        rowcnt += 1;
+
+        // drop the oldest datapoints beyond the width.
+        // with candle, the width is # of candles, so the oldest bucket is dropped as a whole.
+        loop {
+            let overflow = match (args.candle, keys.front(), keys.back()) {
+                (Some(_), Some(front), Some(back)) => back - front >= width as u64,
+                _ => datacnt > width,
+            };
+            if !overflow { break; }
 
-            if datacnt > width {
-                vss[i].0.pop_front();
+            for (vs,_) in vss.iter_mut() {
+                vs.pop_front();
             }
-        }
-        if datacnt > width {
+            let key = keys.pop_front();
+            events.pop_front();
             datacnt -= 1;
+
             if let Some(xstep) = args.xstep {
-              x_start += xstep;
+                // x labels are for candles, if aggregated
+                if args.candle.is_none() || keys.front() != key.as_ref() {
+                    x_start += xstep;
+                }
             }
-        };
+        }
 
         if args.monitor {
-            if let Some(mut cfg) = args.gen_config(&vss) {
-                if let Some(x_label) = cfg.x_label.as_mut() {
-                    x_label.0 = x_start;
-                }
-                let (ret, height) = plot(&vss, cfg);
-                print!("\x1b[{}F\x1b[0J{}", last_height-1, &ret[..ret.len()-1]); // removing the last newline
-                std::io::stdout().flush().unwrap();
-                last_height = height;
-            }
+            let ret = render(&args, &vss, &keys, &events, x_start, terminal_size());
+            overwrite(ret, &mut last_height);
         }
     }
 
-    if let Some(mut cfg) = args.gen_config(&vss) {
-        if let Some(x_label) = cfg.x_label.as_mut() {
-            x_label.0 = x_start;
-        }
-        let ret = plot(&vss, cfg);
+    // candles can't be drawn from columns that never came
+    if let Some(columns) = args.ohlc.filter(|columns| columns.iter().any(|&i| i > vss.len())) {
+        let needed = columns.iter().max().unwrap();
+        let msg = format!("--ohlc needs {} columns of input, but got {}", needed, vss.len());
+        Args::command().error(ErrorKind::ValueValidation, msg).exit();
+    }
+
+    if let Some(ret) = render(&args, &vss, &keys, &events, x_start, terminal_size()) {
         if args.monitor {
-            print!("\x1b[{}F\x1b[0J", last_height-1);
+            print!("{}", rewind(last_height));
         }
         print!("{}", ret.0);
     }
 }
 
+// prints the chart over the previous one of `last_height` lines, leaving the cursor at its last line
+fn overwrite(ret: Option<(String, usize)>, last_height: &mut usize) {
+    if let Some((ret, height)) = ret {
+        print!("{}{}", rewind(*last_height), &ret[..ret.len()-1]); // removing the last newline
+        std::io::stdout().flush().unwrap();
+        *last_height = height;
+    }
+}
+
+// moves the cursor back to the start of the previous chart of `height` lines and clears it.
+// lines are rewrapped by the terminal when resized, so the whole screen is cleared instead.
+fn rewind(height: usize) -> String {
+    if RESIZED.swap(false, Ordering::Relaxed) {
+        "\x1b[H\x1b[2J".to_string()
+    } else {
+        format!("\x1b[{}F\x1b[0J", height-1)
+    }
+}
+
+// draws the series as a single chart, or as a grid of charts if layout is given.
+// the chart is fit into `term` columns and rows, unless the width or height is given.
+// returns None if there's no data to plot yet.
+fn render(args: &Args, vss: &[(VecDeque<f64>,u32)], keys: &VecDeque<u64>, events: &VecDeque<String>, x_start: f64,
+          term: Option<(usize, usize)>) -> Option<(String, usize)> {
+    // draws the series at given indices into a single chart of `args`, dropping oldest `drop` datapoints
+    let draw_once = |args: &Args, vss: &[(VecDeque<f64>,u32)], indices: &[usize], drop: usize| {
+        let vss = indices.iter().map(|&i| {
+            let (vs, color) = &vss[i];
+            (vs.iter().cloned().skip(drop).collect::<VecDeque<_>>(), *color)
+        }).collect::<Vec<_>>();
+        let mut cfg = args.gen_config(&vss)?;
+        if let (Some(x_label), None) = (cfg.x_label.as_mut(), cfg.histogram) {
+            x_label.0 = x_start + x_label.1 * drop as f64;
+        }
+        cfg.names = args.series_names(indices.iter().cloned());
+        cfg.priority = args.series_priority(indices.iter().cloned());
+        if cfg.histogram.is_none() && args.candle.is_none() {
+            cfg.events = events.iter().skip(drop).cloned().enumerate().filter(|(_, label)| !label.is_empty()).collect();
+        }
+        let (rows, width) = (cfg.rows(), cfg.width);
+        let (ret, height) = plot(&vss, cfg);
+        Some((ret, height, rows, width))
+    };
+
+    // draws into `fit` columns and rows, by dropping oldest datapoints and lowering the plot
+    let draw = |vss: &[(VecDeque<f64>,u32)], indices: &[usize], fit: Option<(usize, usize)>| {
+        // at most as high as the rows to fit in, then fit exactly below
+        let args = &Args { max_height: args.max_height.or(fit.map(|(_, lines)| lines)), ..args.clone() };
+        if let (Some((cols, _)), Mode::Hbar, None) = (fit, args.mode(), args.width) {
+            // bars are scaled to the terminal width
+            let args = Args { width: Some(cols), ..args.clone() };
+            return draw_once(&args, vss, indices, 0).map(|(ret, height, ..)| (ret, height));
+        }
+        let (ret, height, rows, width) = draw_once(args, vss, indices, 0)?;
+        let Some((cols, lines)) = fit else { return Some((ret, height)) };
+        if matches!(args.mode(), Mode::Sparkline | Mode::Heatmap | Mode::Hbar) { return Some((ret, height)); }
+
+        let mut args = args.clone();
+        let mut drop = 0;
+        let wide = ret.lines().map(|l| display_width(l.trim_end())).max().unwrap_or(0);
+        if args.width.is_none() && !args.histogram && wide > cols {
+            let len = indices.iter().map(|&i| vss[i].0.len()).max().unwrap_or(0);
+            drop = len.saturating_sub(args.mode().datapoints(width.saturating_sub(wide - cols), indices.len()));
+        }
+        // leaving a line for the cursor
+        if args.height.is_none() && height >= lines && rows > 2 {
+            args.height = Some(rows.saturating_sub(height + 1 - lines).max(2));
+        }
+        if drop == 0 && args.height.is_none() { return Some((ret, height)); }
+        draw_once(&args, vss, indices, drop).map(|(ret, height, ..)| (ret, height))
+    };
+
+    // columns to draw as lines
+    let lines = match &args.line {
+        Some(columns) => columns.iter().filter(|&&c| 0 < c && c <= vss.len()).map(|c| c-1).collect(),
+        None => (0..vss.len()).filter(|i| args.band.is_none_or(|band| !band.contains(&(i+1)))).collect::<Vec<_>>(),
+    };
+
+    if args.ohlc.is_some() || args.candle.is_some() {
+        // open, high, low, close series, all from the first column if not given
+        let columns = args.ohlc.unwrap_or([1; 4]);
+        if columns.iter().any(|&i| i > vss.len()) { return None; }
+        let ohlc = columns.map(|i| vss[i-1].0.clone());
+        let ohlc = if args.candle.is_some() { candles(&ohlc, keys) } else { ohlc };
+        let vss = ohlc.into_iter().map(|vs| (vs, 9)).collect::<Vec<_>>();
+        draw(&vss, &[0, 1, 2, 3], term)
+    } else if let Some([lo, hi]) = args.band {
+        // bounds of the band come first
+        if lo > vss.len() || hi > vss.len() { return None; }
+        let indices = [lo-1, hi-1].into_iter().chain(lines).collect::<Vec<_>>();
+        draw(vss, &indices, term)
+    } else if let Some((rows, cols)) = args.layout {
+        let n = rows * cols;
+        // each panel gets its share of the terminal, less the gaps between
+        let fit = term.map(|(w, h)| (w.saturating_sub(2 * (cols-1)) / cols, ((h + 1).saturating_sub(rows) / rows).max(1)));
+        let mut panels = (0..n).map(|k| {
+            let indices = lines.iter().cloned().skip(k).step_by(n).collect::<Vec<_>>();
+            draw(vss, &indices, fit).map(|(ret, _)| ret)
+        }).collect::<Vec<_>>();
+        // panels without data are left blank as wide as the others to keep them in place,
+        // unless they are the last ones
+        while panels.last().is_some_and(|p| p.is_none()) { panels.pop(); }
+        let wide = panels.iter().flatten().flat_map(|p| p.lines()).map(display_width).max().unwrap_or(0);
+        let panels = panels.into_iter().map(|p| p.unwrap_or(" ".repeat(wide))).collect::<Vec<_>>();
+        (!panels.is_empty()).then(|| grid(&panels, cols))
+    } else {
+        draw(vss, &lines, term)
+    }
+}
+
 fn demo_data(demo :&str) -> Vec<(VecDeque<f64>,u32)> {
     match demo {
         "sincos" => demo_sincos(),
@@ -86,15 +306,15 @@ fn demo_sincos() -> Vec<(VecDeque<f64>,u32)> {
     let width = 80;
 
     let mut v1 = VecDeque::from(vec![0f64; width]);
-    for i in 0 .. width {
+    for (i, v) in v1.iter_mut().enumerate() {
         let pi = std::f64::consts::PI;
-        v1[i] = 3. * (i as f64 * pi * 4.0 / width as f64).sin();
+        *v = 3. * (i as f64 * pi * 4.0 / width as f64).sin();
     }
 
     let mut v2 = VecDeque::from(vec![0f64; width]);
-    for i in 0 .. width {
+    for (i, v) in v2.iter_mut().enumerate() {
         let pi = std::f64::consts::PI;
-        v2[i] = 7. * (i as f64 * pi * 4.0 / width as f64).cos();
+        *v = 7. * (i as f64 * pi * 4.0 / width as f64).cos();
     }
 
     vec![(v1,1), (v2,2)]